@@ -0,0 +1,106 @@
+//! Prints the `multiexp` and `region` spans emitted with the `tracing` feature,
+//! together with the time spent in each of them:
+//!
+//! cargo run --release --example multiexp_tracing --features tracing
+
+extern crate bellman_ce;
+extern crate rand;
+extern crate tracing;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use bellman_ce::multiexp::dense_multiexp;
+use bellman_ce::pairing::bn256::{Bn256, Fr};
+use bellman_ce::pairing::ff::PrimeField;
+use bellman_ce::pairing::{CurveProjective, Engine};
+use bellman_ce::worker::Worker;
+
+use rand::{Rand, SeedableRng, XorShiftRng};
+
+struct SpanState {
+    description: String,
+    busy: Duration,
+    entered_at: Option<Instant>,
+}
+
+/// Tiny subscriber printing every span with its fields once it is closed.
+#[derive(Default)]
+struct PrintingSubscriber {
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanState>>,
+}
+
+struct FieldPrinter<'a>(&'a mut String);
+
+impl<'a> Visit for FieldPrinter<'a> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push_str(&format!(" {}={:?}", field.name(), value));
+    }
+}
+
+impl Subscriber for PrintingSubscriber {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut description = span.metadata().name().to_string();
+        span.record(&mut FieldPrinter(&mut description));
+
+        let state = SpanState { description, busy: Duration::default(), entered_at: None };
+        self.spans.lock().unwrap().insert(id, state);
+
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _: &Id, _: &Record) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event) {}
+
+    fn enter(&self, span: &Id) {
+        if let Some(state) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            state.entered_at = Some(Instant::now());
+        }
+    }
+
+    fn exit(&self, span: &Id) {
+        if let Some(state) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            if let Some(entered_at) = state.entered_at.take() {
+                state.busy += entered_at.elapsed();
+            }
+        }
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        if let Some(state) = self.spans.lock().unwrap().remove(&span.into_u64()) {
+            println!("{:<40} {:?}", state.description, state.busy);
+        }
+
+        true
+    }
+}
+
+fn main() {
+    const SAMPLES: usize = 1 << 16;
+
+    tracing::subscriber::set_global_default(PrintingSubscriber::default())
+        .expect("no other subscriber is installed");
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let bases = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let exponents = (0..SAMPLES).map(|_| Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    dense_multiexp(&pool, &bases, &exponents).expect("lengths match");
+}
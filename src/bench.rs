@@ -0,0 +1,82 @@
+//! Timing of `dense_multiexp` for downstream benchmark harnesses, so that
+//! they don't have to replicate the setup of the ignored speed tests.
+
+use std::time::Instant;
+
+use crate::pairing::CurveAffine;
+use crate::pairing::ff::PrimeField;
+
+use crate::multiexp::{cache_window_size, dense_multiexp};
+use crate::worker::Worker;
+use crate::SynthesisError;
+
+/// Timings of the measured runs of `bench_multiexp`, in nanoseconds per run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchResult {
+    /// Window size in bits chosen by `dense_multiexp`
+    pub c: u32,
+    /// Number of measured runs
+    pub iters: usize,
+    pub min_ns: u64,
+    pub median_ns: u64,
+    pub mean_ns: u64
+}
+
+/// Run `dense_multiexp` `warmups` times untimed, then `iters` times timed.
+/// Fails if any of the runs fails; with no timed runs all timings are zero.
+pub fn bench_multiexp<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<G::Scalar as PrimeField>::Repr],
+    warmups: usize,
+    iters: usize
+) -> Result<BenchResult, SynthesisError>
+{
+    for _ in 0..warmups {
+        dense_multiexp(pool, bases, exponents)?;
+    }
+
+    let mut samples = Vec::with_capacity(iters);
+    for _ in 0..iters {
+        let start = Instant::now();
+        dense_multiexp(pool, bases, exponents)?;
+        samples.push(start.elapsed().as_nanos() as u64);
+    }
+    samples.sort_unstable();
+
+    Ok(BenchResult {
+        c: cache_window_size::<G>(pool, exponents.len()),
+        iters,
+        min_ns: samples.first().copied().unwrap_or(0),
+        median_ns: samples.get(iters / 2).copied().unwrap_or(0),
+        mean_ns: samples.iter().sum::<u64>().checked_div(iters as u64).unwrap_or(0)
+    })
+}
+
+#[test]
+fn test_bench_multiexp() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::{Engine, CurveProjective};
+    use crate::pairing::ff::ScalarEngine;
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let v = (0..(1 << 8)).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..(1 << 8)).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let result = bench_multiexp(&pool, &g, &v, 1, 5).unwrap();
+
+    assert_eq!(result.iters, 5);
+    assert!(result.min_ns > 0);
+    assert!(result.min_ns <= result.median_ns);
+    assert!(result.min_ns <= result.mean_ns);
+
+    let empty = bench_multiexp(&pool, &g, &v, 0, 0).unwrap();
+    assert_eq!((empty.min_ns, empty.median_ns, empty.mean_ns), (0, 0, 0));
+
+    match bench_multiexp(&pool, &g[1..], &v, 0, 1) {
+        Err(SynthesisError::LengthMismatch { .. }) => {},
+        _ => panic!("expected a length mismatch")
+    }
+}
@@ -0,0 +1,69 @@
+//! Detection of the data cache sizes of the CPU, used to keep the per-thread
+//! working set of the multiexp inside the cache. Sizes are read from sysfs on
+//! Linux and are unknown everywhere else.
+
+use std::fs;
+use std::path::Path;
+
+/// Data cache sizes in bytes, as seen by the first CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheSizes {
+    pub l1d: Option<usize>,
+    pub l2: Option<usize>,
+    pub l3: Option<usize>,
+}
+
+impl CacheSizes {
+    pub fn detect() -> CacheSizes {
+        let mut sizes = CacheSizes::default();
+
+        if cfg!(target_os = "linux") {
+            let root = Path::new("/sys/devices/system/cpu/cpu0/cache");
+            for index in 0.. {
+                let dir = root.join(format!("index{}", index));
+                if !dir.exists() {
+                    break;
+                }
+
+                let read = |name: &str| fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string());
+                let (level, kind, size) = match (read("level"), read("type"), read("size").as_deref().and_then(parse_size)) {
+                    (Some(level), Some(kind), Some(size)) => (level, kind, size),
+                    _ => continue
+                };
+                if kind == "Instruction" {
+                    continue;
+                }
+
+                match level.as_str() {
+                    "1" => sizes.l1d = Some(size),
+                    "2" => sizes.l2 = Some(size),
+                    "3" => sizes.l3 = Some(size),
+                    _ => {}
+                }
+            }
+        }
+
+        sizes
+    }
+}
+
+/// Parse sizes like `48K`, `2048K` or `32M`.
+fn parse_size(size: &str) -> Option<usize> {
+    let (digits, multiplier) = match size.chars().last()? {
+        'K' => (&size[..size.len() - 1], 1 << 10),
+        'M' => (&size[..size.len() - 1], 1 << 20),
+        'G' => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1)
+    };
+
+    digits.parse::<usize>().ok().map(|n| n * multiplier)
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("48K"), Some(48 << 10));
+    assert_eq!(parse_size("32M"), Some(32 << 20));
+    assert_eq!(parse_size("512"), Some(512));
+    assert_eq!(parse_size("K"), None);
+    assert_eq!(parse_size(""), None);
+}
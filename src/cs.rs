@@ -169,7 +169,21 @@ pub enum SynthesisError {
     /// During verification, our verifying key was malformed.
     MalformedVerifyingKey,
     /// During CRS generation, we observed an unconstrained auxillary variable
-    UnconstrainedVariable
+    UnconstrainedVariable,
+    /// During multiexp, the number of bases and exponents did not match
+    LengthMismatch { bases: usize, exponents: usize },
+    /// During multiexp, a worker thread panicked and its partial result was lost
+    WorkerFailure,
+    /// During multiexp, the computation was cancelled by the caller
+    Cancelled,
+    /// During multiexp, the requested window size was unusable
+    InvalidWindow(u32),
+    /// During multiexp, the base at this index was not a point on the curve
+    InvalidBase(usize),
+    /// During multiexp, the base at this index was not in the prime order subgroup
+    InvalidSubgroup(usize),
+    /// During multiexp, the split point was past the end of the bases
+    SplitOutOfBounds { split_at: usize, len: usize }
 }
 
 impl From<io::Error> for SynthesisError {
@@ -188,7 +202,14 @@ impl SynthesisError {
             SynthesisError::UnexpectedIdentity => "encountered an identity element in the CRS",
             SynthesisError::IoError(_) => "encountered an I/O error",
             SynthesisError::MalformedVerifyingKey => "malformed verifying key",
-            SynthesisError::UnconstrainedVariable => "auxillary variable was unconstrained"
+            SynthesisError::UnconstrainedVariable => "auxillary variable was unconstrained",
+            SynthesisError::LengthMismatch { .. } => "number of bases and exponents do not match",
+            SynthesisError::WorkerFailure => "a multiexp worker thread panicked",
+            SynthesisError::Cancelled => "multiexp was cancelled",
+            SynthesisError::InvalidWindow(_) => "invalid multiexp window size",
+            SynthesisError::InvalidBase(_) => "multiexp base is not on the curve",
+            SynthesisError::InvalidSubgroup(_) => "multiexp base is not in the prime order subgroup",
+            SynthesisError::SplitOutOfBounds { .. } => "multiexp split point is out of bounds"
         }
     }
 }
@@ -204,6 +225,16 @@ impl fmt::Display for SynthesisError {
         if let &SynthesisError::IoError(ref e) = self {
             write!(f, "I/O error: ")?;
             e.fmt(f)
+        } else if let &SynthesisError::LengthMismatch { bases, exponents } = self {
+            write!(f, "{}: {} bases, {} exponents", self.self_description(), bases, exponents)
+        } else if let &SynthesisError::InvalidWindow(c) = self {
+            write!(f, "{}: {} bits", self.self_description(), c)
+        } else if let &SynthesisError::InvalidBase(idx) = self {
+            write!(f, "{}: index {}", self.self_description(), idx)
+        } else if let &SynthesisError::InvalidSubgroup(idx) = self {
+            write!(f, "{}: index {}", self.self_description(), idx)
+        } else if let &SynthesisError::SplitOutOfBounds { split_at, len } = self {
+            write!(f, "{}: {} of {} bases", self.self_description(), split_at, len)
         } else {
             write!(f, "{}", self.self_description())
         }
@@ -20,8 +20,15 @@ pub mod gm17;
 pub mod sonic;
 
 mod group;
+mod cache;
 pub mod source;
-mod multiexp;
+pub mod multiexp;
+
+#[cfg(all(feature = "perf-counters", target_os = "linux"))]
+pub mod perf;
+
+#[cfg(feature = "bench")]
+pub mod bench;
 
 #[cfg(test)]
 mod tests;
@@ -11,30 +11,42 @@ extern crate crossbeam;
 use std::future::{Future};
 use std::task::{Context, Poll};
 use std::pin::{Pin};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Thread};
 
 use self::crossbeam::thread::{Scope};
+#[cfg(all(feature = "linux-affinity", target_os = "linux"))]
+use self::crossbeam::thread::{ScopedJoinHandle};
+#[cfg(all(feature = "linux-affinity", target_os = "linux"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub use crate::cache::CacheSizes;
 
 use self::futures::future::{lazy};
 use self::futures::channel::oneshot::{channel, Sender, Receiver};
 use self::futures::executor::{block_on};
 use self::futures::executor::{ThreadPool};
+use self::futures::task::{waker, ArcWake};
+
+type Task = Box<dyn FnOnce() + Send>;
 
 #[derive(Clone)]
 pub struct Worker {
     cpus: usize,
-    pool: ThreadPool
+    pool: ThreadPool,
+    cache: CacheSizes,
+    min_chunk_size: usize
 }
 
 
 impl Worker {
-    // We don't expose this outside the library so that
-    // all `Worker` instances have the same number of
-    // CPUs configured.
-    
     pub(crate) fn new_with_cpus(cpus: usize) -> Worker {
+        let cpus = std::cmp::max(cpus, 1);
         Worker {
             cpus: cpus,
             pool: ThreadPool::builder().pool_size(cpus).create().expect("should create a thread pool for futures execution"),
+            cache: CacheSizes::detect(),
+            min_chunk_size: 1
         }
     }
 
@@ -42,10 +54,49 @@ impl Worker {
         Self::new_with_cpus(num_cpus::get())
     }
 
+    /// Worker with its own pool of `cpus` threads, regardless of the number
+    /// of CPUs available, e.g. to pin it down in tests or to oversubscribe.
+    pub fn with_cpus(cpus: usize) -> Worker {
+        Self::new_with_cpus(cpus)
+    }
+
+    /// Make `scope` split work into chunks of at least `min_chunk_size`
+    /// items, so that small inputs are not spread over a task per item.
+    pub fn with_min_chunk_size(mut self, min_chunk_size: usize) -> Worker {
+        self.min_chunk_size = std::cmp::max(min_chunk_size, 1);
+        self
+    }
+
     pub fn log_num_cpus(&self) -> u32 {
         log2_floor(self.cpus)
     }
 
+    /// Number of threads `scope` splits work between.
+    pub fn num_cpus(&self) -> usize {
+        self.cpus
+    }
+
+    /// Data cache sizes detected when the worker was created.
+    pub fn cache_sizes(&self) -> CacheSizes {
+        self.cache
+    }
+
+    /// Run `f` with a worker that splits `scope` work between `cpus` threads,
+    /// leaving this one untouched. The futures thread pool is shared rather
+    /// than rebuilt, so tasks submitted via `compute` keep its size.
+    pub fn with_temporary_cpus<F, R>(&self, cpus: usize, f: F) -> R
+        where F: FnOnce(&Worker) -> R
+    {
+        let worker = Worker {
+            cpus: std::cmp::max(cpus, 1),
+            pool: self.pool.clone(),
+            cache: self.cache,
+            min_chunk_size: self.min_chunk_size
+        };
+
+        f(&worker)
+    }
+
     pub fn compute<F, T, E>(
         &self, f: F
     ) -> WorkerFuture<T, E>
@@ -54,16 +105,23 @@ impl Worker {
               E: Send + 'static
     {
         let (sender, receiver) = channel();
-        let lazy_future = lazy(move |_| {
+        let task: Task = Box::new(move || {
             let res = f(); 
 
             if !sender.is_canceled() {
                 let _ = sender.send(res);
             }
         });
+        let task = Arc::new(Mutex::new(Some(task)));
+
+        let pending = task.clone();
+        let lazy_future = lazy(move |_| {
+            run_pending(&pending);
+        });
 
         let worker_future = WorkerFuture {
-            receiver
+            receiver,
+            task
         };
 
         self.pool.spawn_ok(lazy_future);
@@ -71,6 +129,32 @@ impl Worker {
         worker_future
     }
 
+    /// Drive `future` to completion on the calling thread. Unlike the
+    /// futures executor this may be entered from a task of this worker.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let waker = waker(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+
+        loop {
+            if let Poll::Ready(v) = future.as_mut().poll(&mut cx) {
+                return v;
+            }
+            thread::park();
+        }
+    }
+
+    /// Size of the chunks that `scope` splits `elements` items into.
+    pub fn get_chunk_size(&self, elements: usize) -> usize {
+        let chunk_size = if elements < self.cpus {
+            1
+        } else {
+            elements / self.cpus
+        };
+
+        std::cmp::max(chunk_size, self.min_chunk_size)
+    }
+
     pub fn scope<'a, F, R>(
         &self,
         elements: usize,
@@ -78,20 +162,121 @@ impl Worker {
     ) -> R
         where F: FnOnce(&Scope<'a>, usize) -> R
     {
-        let chunk_size = if elements < self.cpus {
-            1
-        } else {
-            elements / self.cpus
-        };
+        let chunk_size = self.get_chunk_size(elements);
 
         crossbeam::scope(|scope| {
             f(scope, chunk_size)
         }).expect("must run")
     }
+
+    /// Same as `scope`, but the `i`-th thread spawned is bound to the `i`-th
+    /// CPU, modulo their number, that the calling thread is allowed to run
+    /// on. Binding is done with `sched_setaffinity`; a thread that cannot be
+    /// bound, e.g. because the syscall is filtered, runs unbound.
+    #[cfg(all(feature = "linux-affinity", target_os = "linux"))]
+    pub fn scope_pinned<'a, F, R>(
+        &self,
+        elements: usize,
+        f: F
+    ) -> R
+        where F: for<'s> FnOnce(&PinnedScope<'a, 's>, usize) -> R
+    {
+        let chunk_size = self.get_chunk_size(elements);
+        let cpus = affinity::allowed_cpus();
+
+        crossbeam::scope(|scope| {
+            let scope = PinnedScope {
+                scope,
+                cpus: &cpus,
+                next: AtomicUsize::new(0)
+            };
+            f(&scope, chunk_size)
+        }).expect("must run")
+    }
+}
+
+/// Scope handed out by `Worker::scope_pinned`, binding every thread it
+/// spawns to the next CPU in turn.
+#[cfg(all(feature = "linux-affinity", target_os = "linux"))]
+pub struct PinnedScope<'a, 's> {
+    scope: &'s Scope<'a>,
+    cpus: &'s [usize],
+    next: AtomicUsize
+}
+
+#[cfg(all(feature = "linux-affinity", target_os = "linux"))]
+impl<'a, 's> PinnedScope<'a, 's> {
+    pub fn spawn<F, T>(&self, f: F) -> ScopedJoinHandle<'s, T>
+        where F: FnOnce(&Scope<'a>) -> T + Send + 'a,
+              T: Send + 'a
+    {
+        let i = self.next.fetch_add(1, Ordering::Relaxed);
+        let cpu = if self.cpus.is_empty() { None } else { Some(self.cpus[i % self.cpus.len()]) };
+
+        self.scope.spawn(move |scope| {
+            if let Some(cpu) = cpu {
+                affinity::pin_current_thread(cpu);
+            }
+            f(scope)
+        })
+    }
+}
+
+#[cfg(all(feature = "linux-affinity", target_os = "linux"))]
+mod affinity {
+    extern crate libc;
+
+    use std::mem;
+
+    /// CPUs the calling thread may run on, in increasing order. Empty if they
+    /// cannot be queried.
+    pub(crate) fn allowed_cpus() -> Vec<usize> {
+        unsafe {
+            let mut set: libc::cpu_set_t = mem::zeroed();
+            if libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+                return vec![];
+            }
+
+            (0..libc::CPU_SETSIZE as usize).filter(|&cpu| libc::CPU_ISSET(cpu, &set)).collect()
+        }
+    }
+
+    /// Restrict the calling thread to `cpu`. Returns whether that worked.
+    pub(crate) fn pin_current_thread(cpu: usize) -> bool {
+        unsafe {
+            let mut set: libc::cpu_set_t = mem::zeroed();
+            libc::CPU_SET(cpu, &mut set);
+
+            libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) == 0
+        }
+    }
+}
+
+// Run the task behind `task` unless some thread has already taken it.
+fn run_pending(task: &Mutex<Option<Task>>) -> bool {
+    let task = task.lock().expect("task lock is not poisoned").take();
+    match task {
+        Some(task) => {
+            task();
+            true
+        },
+        None => false
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl ArcWake for ThreadWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.unpark();
+    }
 }
 
 pub struct WorkerFuture<T, E> {
-    receiver: Receiver<Result<T, E>>
+    receiver: Receiver<Result<T, E>>,
+    // Task computing the result until a thread of the pool, or one calling
+    // `run_here`, takes it
+    task: Arc<Mutex<Option<Task>>>
 }
 
 impl<T: Send + 'static, E: Send + 'static> Future for WorkerFuture<T, E> {
@@ -119,6 +304,24 @@ impl<T: Send + 'static, E: Send + 'static> WorkerFuture<T, E> {
     pub fn wait(self) -> <Self as Future>::Output {
         block_on(self)
     }
+
+    /// Future that is already resolved to `result`, for work that was done
+    /// on the calling thread.
+    pub fn ready(result: Result<T, E>) -> Self {
+        let (sender, receiver) = channel();
+        let _ = sender.send(result);
+
+        WorkerFuture {
+            receiver,
+            task: Arc::new(Mutex::new(None))
+        }
+    }
+
+    /// Run the task behind this future on the calling thread if no thread of
+    /// the pool has started it yet. Returns whether it did.
+    pub fn run_here(&self) -> bool {
+        run_pending(&self.task)
+    }
 }
 
 fn log2_floor(num: usize) -> u32 {
@@ -174,3 +377,36 @@ fn test_trivial_spawning() {
 
     let _ = block_on(fut);
 }
+
+#[test]
+fn test_min_chunk_size() {
+    let worker = Worker::with_cpus(4);
+    assert_eq!(worker.get_chunk_size(3), 1);
+    assert_eq!(worker.get_chunk_size(1 << 10), 1 << 8);
+
+    let worker = worker.with_min_chunk_size(1 << 9);
+    assert_eq!(worker.get_chunk_size(3), 1 << 9);
+    assert_eq!(worker.get_chunk_size(1 << 10), 1 << 9);
+    assert_eq!(worker.get_chunk_size(1 << 12), 1 << 10);
+}
+
+#[cfg(all(feature = "linux-affinity", target_os = "linux"))]
+#[test]
+fn test_scope_pinned() {
+    let allowed = affinity::allowed_cpus();
+    assert!(!allowed.is_empty());
+
+    let worker = Worker::with_cpus(4);
+    let mut pinned = vec![vec![]; 4];
+    worker.scope_pinned(4, |scope, _| {
+        for pinned in pinned.iter_mut() {
+            scope.spawn(move |_| {
+                *pinned = affinity::allowed_cpus();
+            });
+        }
+    });
+
+    for (i, pinned) in pinned.iter().enumerate() {
+        assert_eq!(pinned, &vec![allowed[i % allowed.len()]]);
+    }
+}
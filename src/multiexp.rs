@@ -1,6 +1,7 @@
 use crate::pairing::{
     CurveAffine,
     CurveProjective,
+    EncodedPoint,
     Engine
 };
 
@@ -10,7 +11,15 @@ use crate::pairing::ff::{
     PrimeFieldRepr,
     ScalarEngine};
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use super::source::*;
 use std::future::{Future};
 use std::task::{Context, Poll};
@@ -18,8 +27,9 @@ use std::pin::{Pin};
 
 extern crate futures;
 
-use self::futures::future::{join_all, JoinAll};
-use self::futures::executor::block_on;
+use self::futures::future::{lazy, FutureExt};
+use self::futures::stream::{FuturesUnordered, Stream};
+use self::futures::executor::{block_on, LocalPool};
 
 use super::worker::{Worker, WorkerFuture};
 
@@ -27,6 +37,46 @@ use super::SynthesisError;
 
 use cfg_if;
 
+cfg_if! {
+    if #[cfg(feature = "tracing")] {
+        extern crate tracing;
+
+        use self::tracing::Span;
+
+        fn multiexp_span(num_exponents: usize, c: u32) -> Span {
+            tracing::info_span!("multiexp", num_exponents, c)
+        }
+
+        fn region_span(parent: &Span, skip: u32, c: u32) -> Span {
+            tracing::info_span!(parent: parent, "region", skip, c)
+        }
+    } else {
+        // Stand-ins with the parts of the `tracing` API used below, so that
+        // the instrumentation compiles to nothing without the feature.
+        struct Span;
+
+        struct Entered;
+
+        impl Span {
+            fn current() -> Span {
+                Span
+            }
+
+            fn enter(&self) -> Entered {
+                Entered
+            }
+        }
+
+        fn multiexp_span(_num_exponents: usize, _c: u32) -> Span {
+            Span
+        }
+
+        fn region_span(_parent: &Span, _skip: u32, _c: u32) -> Span {
+            Span
+        }
+    }
+}
+
 /// This genious piece of code works in the following way:
 /// - choose `c` - the bit length of the region that one thread works on
 /// - make `2^c - 1` buckets and initialize them with `G = infinity` (that's equivalent of zero)
@@ -65,84 +115,159 @@ fn multiexp_inner<Q, D, G, S>(
     exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
     skip: u32,
     c: u32,
-    handle_trivial: bool
+    control: RegionControl
 ) -> WorkerFuture< <G as CurveAffine>::Projective, SynthesisError>
     where for<'a> &'a Q: QueryDensity,
           D: Send + Sync + 'static + Clone + AsRef<Q>,
           G: CurveAffine,
           S: SourceBuilder<G>
 {
+    let parent = Span::current();
+
     // Perform this region of the multiexp
-    let this = {
-        // let bases = bases.clone();
-        // let exponents = exponents.clone();
-        // let density_map = density_map.clone();
+    pool.compute(move || catch_worker_panic(move || {
+        let span = region_span(&parent, skip, c);
+        let _enter = span.enter();
 
-        // This is a Pippenger’s algorithm
-        pool.compute(move || {
-            // Accumulate the result
-            let mut acc = G::Projective::zero();
+        multiexp_region(bases, &density_map, &exponents, skip, c, &control)
+    }))
+}
 
-            // Build a source for the bases
-            let mut bases = bases.new();
+/// Bucket one region of the multiexp, the bits `[skip, skip + c)` of every
+/// exponent, and sum the buckets up.
+fn multiexp_region<Q, D, G, S>(
+    bases: S,
+    density_map: &D,
+    exponents: &[<G::Scalar as PrimeField>::Repr],
+    skip: u32,
+    c: u32,
+    control: &RegionControl
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    // The highest region may be narrower, it only needs buckets for its own bits
+    let c = std::cmp::min(c, <G::Engine as ScalarEngine>::Fr::NUM_BITS.saturating_sub(skip));
 
-            // Create buckets to place remainders s mod 2^c,
-            // it will be 2^c - 1 buckets (no bucket for zeroes)
+    // Accumulate the result, with the bases of scalar one only
+    // added directly in the lowest region
+    let mut acc = G::Projective::zero();
+    let handle_trivial = skip == 0;
 
-            // Create space for the buckets
-            let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+    // Build a source for the bases
+    let mut bases = bases.new();
 
-            let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
-            let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
+    // Create buckets to place remainders s mod 2^c,
+    // it will be 2^c - 1 buckets (no bucket for zeroes)
 
-            // Sort the bases into buckets
-            for (&exp, density) in exponents.iter().zip(density_map.as_ref().iter()) {
-                // Go over density and exponents
-                if density {
-                    if exp == zero {
-                        bases.skip(1)?;
-                    } else if exp == one {
-                        if handle_trivial {
-                            bases.add_assign_mixed(&mut acc)?;
-                        } else {
-                            bases.skip(1)?;
-                        }
-                    } else {
-                        // Place multiplication into the bucket: Separate s * P as 
-                        // (s/2^c) * P + (s mod 2^c) P
-                        // First multiplication is c bits less, so one can do it,
-                        // sum results from different buckets and double it c times,
-                        // then add with (s mod 2^c) P parts
-                        let mut exp = exp;
-                        exp.shr(skip);
-                        let exp = exp.as_ref()[0] % (1 << c);
+    // Create space for the buckets
+    let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
 
-                        if exp != 0 {
-                            bases.add_assign_mixed(&mut buckets[(exp - 1) as usize])?;
-                        } else {
-                            bases.skip(1)?;
-                        }
-                    }
-                }
+    let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
+    let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
+
+    // Sort the bases into buckets
+    let mut processed = 0;
+    for (&exp, density) in exponents.iter().zip(density_map.as_ref().iter()) {
+        if control.cancelled.is_cancelled() {
+            return Err(SynthesisError::Cancelled);
+        }
+        if let Some(progress) = control.progress.as_ref() {
+            processed += 1;
+            if processed == PROGRESS_STEP {
+                progress.advance(processed);
+                processed = 0;
             }
+        }
+        // Go over density and exponents
+        if density {
+            if exp == zero {
+                bases.skip(1)?;
+            } else if exp == one {
+                if handle_trivial {
+                    bases.add_assign_mixed(&mut acc)?;
+                } else {
+                    bases.skip(1)?;
+                }
+            } else {
+                // Place multiplication into the bucket: Separate s * P as 
+                // (s/2^c) * P + (s mod 2^c) P
+                // First multiplication is c bits less, so one can do it,
+                // sum results from different buckets and double it c times,
+                // then add with (s mod 2^c) P parts
+                let exp = Limbs64::window(&exp, skip, c);
 
-            // Summation by parts
-            // e.g. 3a + 2b + 1c = a +
-            //                    (a) + b +
-            //                    ((a) + b) + c
-            let mut running_sum = G::Projective::zero();
-            for exp in buckets.into_iter().rev() {
-                running_sum.add_assign(&exp);
-                acc.add_assign(&running_sum);
+                if exp != 0 && c == 1 {
+                    // A single bucket of weight one, add to the result directly
+                    bases.add_assign_mixed(&mut acc)?;
+                } else if exp != 0 {
+                    bases.add_assign_mixed(&mut buckets[(exp - 1) as usize])?;
+                } else {
+                    bases.skip(1)?;
+                }
             }
+        }
+    }
 
-            Ok(acc)
-        })
-    };
+    if let Some(progress) = control.progress.as_ref() {
+        progress.advance(processed);
+    }
 
-    this
+    // Summation by parts
+    // e.g. 3a + 2b + 1c = a +
+    //                    (a) + b +
+    //                    ((a) + b) + c
+    let mut running_sum = G::Projective::zero();
+    for exp in buckets.into_iter().rev() {
+        running_sum.add_assign(&exp);
+        acc.add_assign(&running_sum);
+    }
+
+    Ok(acc)
+}
+
+
+/// Callback receiving `(bases_processed, total)` from `multiexp_with_progress`.
+/// Every region goes over all the bases, so `total` is the number of bases
+/// times the number of regions.
+pub type ProgressCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Number of bases a region task goes over between progress reports.
+pub const PROGRESS_STEP: usize = 1 << 16;
+
+struct Progress {
+    callback: ProgressCallback,
+    total: usize,
+    processed: Mutex<usize>
+}
+
+impl Progress {
+    fn advance(&self, processed: usize) {
+        // Report under the lock, so that the counts reach the callback in order
+        if let Ok(mut total_processed) = self.processed.lock() {
+            *total_processed += processed;
+            (self.callback)(*total_processed, self.total);
+        }
+    }
+}
+
+/// State shared by the region tasks of one multiexp.
+#[derive(Clone)]
+struct RegionControl {
+    cancelled: Arc<AtomicBool>,
+    progress: Option<Arc<Progress>>
 }
 
+/// Run the work of one pool task, turning a panic inside it into
+/// `SynthesisError::WorkerFailure` instead of a dropped result that would
+/// panic again in whoever waits on the task.
+fn catch_worker_panic<T, F>(f: F) -> Result<T, SynthesisError>
+    where F: FnOnce() -> Result<T, SynthesisError>
+{
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or(Err(SynthesisError::WorkerFailure))
+}
 
 cfg_if! {
     if #[cfg(feature = "nightly")] {
@@ -154,14 +279,14 @@ cfg_if! {
             exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
             skip: u32,
             c: u32,
-            handle_trivial: bool
+            control: RegionControl
         ) -> WorkerFuture< <G as CurveAffine>::Projective, SynthesisError>
             where for<'a> &'a Q: QueryDensity,
                 D: Send + Sync + 'static + Clone + AsRef<Q>,
                 G: CurveAffine,
                 S: SourceBuilder<G>
         {
-            multiexp_inner_with_prefetch(pool, bases, density_map, exponents, skip, c, handle_trivial)
+            multiexp_inner_with_prefetch(pool, bases, density_map, exponents, skip, c, control)
         }
     } else {
         #[inline(always)]
@@ -172,14 +297,14 @@ cfg_if! {
             exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
             skip: u32,
             c: u32,
-            handle_trivial: bool
+            control: RegionControl
         ) -> WorkerFuture< <G as CurveAffine>::Projective, SynthesisError>
             where for<'a> &'a Q: QueryDensity,
                 D: Send + Sync + 'static + Clone + AsRef<Q>,
                 G: CurveAffine,
                 S: SourceBuilder<G>
         {
-            multiexp_inner(pool, bases, density_map, exponents, skip, c, handle_trivial)
+            multiexp_inner(pool, bases, density_map, exponents, skip, c, control)
         }
     }  
 }
@@ -197,7 +322,7 @@ fn multiexp_inner_with_prefetch<Q, D, G, S>(
     exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>,
     skip: u32,
     c: u32,
-    handle_trivial: bool
+    control: RegionControl
 ) -> WorkerFuture< <G as CurveAffine>::Projective, SynthesisError>
     where for<'a> &'a Q: QueryDensity,
           D: Send + Sync + 'static + Clone + AsRef<Q>,
@@ -207,10 +332,17 @@ fn multiexp_inner_with_prefetch<Q, D, G, S>(
     use prefetch::prefetch::*;
     // Perform this region of the multiexp
     let this = {
+        let parent = Span::current();
+
         // This is a Pippenger’s algorithm
-        pool.compute(move || {
-            // Accumulate the result
+        pool.compute(move || catch_worker_panic(move || {
+            let span = region_span(&parent, skip, c);
+            let _enter = span.enter();
+
+            // Accumulate the result, with the bases of scalar one only
+            // added directly in the lowest region
             let mut acc = G::Projective::zero();
+            let handle_trivial = skip == 0;
 
             // Build a source for the bases
             let mut bases = bases.new();
@@ -225,17 +357,24 @@ fn multiexp_inner_with_prefetch<Q, D, G, S>(
             let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
             let padding = Arc::new(vec![zero]);
 
-            let mask = 1 << c;
-
             // Sort the bases into buckets
+            let mut processed = 0;
             for ((&exp, &next_exp), density) in exponents.iter()
                         .zip(exponents.iter().skip(1).chain(padding.iter()))
                         .zip(density_map.as_ref().iter()) {
+                if control.cancelled.is_cancelled() {
+                    return Err(SynthesisError::Cancelled);
+                }
+                if let Some(progress) = control.progress.as_ref() {
+                    processed += 1;
+                    if processed == PROGRESS_STEP {
+                        progress.advance(processed);
+                        processed = 0;
+                    }
+                }
                 // no matter what happens - prefetch next bucket
                 if next_exp != zero && next_exp != one {
-                    let mut next_exp = next_exp;
-                    next_exp.shr(skip);
-                    let next_exp = next_exp.as_ref()[0] % mask;
+                    let next_exp = Limbs64::window(&next_exp, skip, c);
                     if next_exp != 0 {
                         let p: *const <G as CurveAffine>::Projective = &buckets[(next_exp - 1) as usize];
                         prefetch::<Write, High, Data, _>(p);
@@ -258,9 +397,7 @@ fn multiexp_inner_with_prefetch<Q, D, G, S>(
                         // First multiplication is c bits less, so one can do it,
                         // sum results from different buckets and double it c times,
                         // then add with (s mod 2^c) P parts
-                        let mut exp = exp;
-                        exp.shr(skip);
-                        let exp = exp.as_ref()[0] % mask;
+                        let exp = Limbs64::window(&exp, skip, c);
 
                         if exp != 0 {
                             bases.add_assign_mixed(&mut buckets[(exp - 1) as usize])?;
@@ -271,6 +408,10 @@ fn multiexp_inner_with_prefetch<Q, D, G, S>(
                 }
             }
 
+            if let Some(progress) = control.progress.as_ref() {
+                progress.advance(processed);
+            }
+
             // Summation by parts
             // e.g. 3a + 2b + 1c = a +
             //                    (a) + b +
@@ -282,14 +423,61 @@ fn multiexp_inner_with_prefetch<Q, D, G, S>(
             }
 
             Ok(acc)
-        })
+        }))
     };
     
     this
 }
 
+/// Scalars converted to their representations once, to be shared by all the
+/// multiexps that use them instead of converting them for every call.
+#[derive(Clone)]
+pub struct ScalarCache<F: PrimeField> {
+    reprs: Arc<Vec<F::Repr>>
+}
+
+impl<F: PrimeField> ScalarCache<F> {
+    /// Convert `scalars` out of Montgomery form in parallel.
+    pub fn new(pool: &Worker, scalars: & [F]) -> Self {
+        let mut reprs = vec![F::Repr::default(); scalars.len()];
+        pool.scope(scalars.len(), |scope, chunk| {
+            for (scalar, repr) in scalars.chunks(chunk)
+                        .zip(reprs.chunks_mut(chunk)) {
+                scope.spawn(move |_| {
+                    for (scalar, repr) in scalar.iter().zip(repr.iter_mut()) {
+                        *repr = scalar.into_repr();
+                    }
+                });
+            }
+        });
+
+        ScalarCache {
+            reprs: Arc::new(reprs)
+        }
+    }
+
+    /// The cached representations, as accepted by `multiexp`.
+    pub fn reprs(&self) -> Arc<Vec<F::Repr>> {
+        self.reprs.clone()
+    }
+}
+
+/// Heuristic bit length of the region processed by one task for `num_exponents` pairs,
+/// always within `1..=MAX_WINDOW_SIZE`.
+fn window_size(num_exponents: usize) -> u32 {
+    if num_exponents < 32 {
+        3u32
+    } else {
+        // take the logarithm of the full usize; casting num_exponents to u32 first wraps to zero at 2^32
+        let c = (num_exponents as f64).ln().ceil() as u32;
+
+        c.clamp(1, MAX_WINDOW_SIZE)
+    }
+}
+
 /// Perform multi-exponentiation. The caller is responsible for ensuring the
-/// query size is the same as the number of exponents.
+/// query size is the same as the number of exponents; when the density map
+/// knows its size a mismatch resolves to `SynthesisError::LengthMismatch`.
 pub fn multiexp<Q, D, G, S>(
     pool: &Worker,
     bases: S,
@@ -301,335 +489,6183 @@ pub fn multiexp<Q, D, G, S>(
           G: CurveAffine,
           S: SourceBuilder<G>
 {
-    let c = if exponents.len() < 32 {
-        3u32
-    } else {
-        (f64::from(exponents.len() as u32)).ln().ceil() as u32
-    };
-
-    if let Some(query_size) = density_map.as_ref().get_query_size() {
-        // If the density map has a known query size, it should not be
-        // inconsistent with the number of exponents.
-
-        assert!(query_size == exponents.len());
-    }
+    let c = window_size(exponents.len());
 
-    let mut skip = 0;
-    let mut futures = Vec::with_capacity((<G::Engine as ScalarEngine>::Fr::NUM_BITS / c + 1) as usize);
+    multiexp_with_window(pool, bases, density_map, exponents, c)
+}
 
-    while skip < <G::Engine as ScalarEngine>::Fr::NUM_BITS {
-        let chunk_future = if skip == 0 {
-            multiexp_inner_impl(pool, bases.clone(), density_map.clone(), exponents.clone(), 0, c, true)
-        } else {
-            multiexp_inner_impl(pool, bases.clone(), density_map.clone(), exponents.clone(), skip, c, false)
+/// Perform multi-exponentiation with a window of `c` bits chosen by the caller
+/// instead of the `multiexp` heuristic. `c` must be in `1..=MAX_WINDOW_SIZE`,
+/// otherwise the result is `SynthesisError::InvalidWindow`. `NUM_BITS` does not have to be
+/// a multiple of `c`: the most significant region is then narrower and just
+/// reads zero bits past the end of the scalar.
+pub fn multiexp_with_window<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    c: u32
+) -> ChunksJoiner< <G as CurveAffine>::Projective >
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    if !(1..=MAX_WINDOW_SIZE).contains(&c) {
+        return ChunksJoiner {
+            regions: vec![WorkerFuture::ready(Err(SynthesisError::InvalidWindow(c)))],
+            acc: None,
+            c,
+            cancelled: Arc::new(AtomicBool::new(false))
         };
-
-        futures.push(chunk_future);
-        skip += c;
     }
 
-    let join = join_all(futures);
-
-    ChunksJoiner {
-        join,
-        c
-    } 
+    multiexp_lowest_regions(pool, bases, density_map, exponents, c, usize::MAX, None)
 }
 
-pub struct ChunksJoiner<G: CurveProjective> {
-    join: JoinAll< WorkerFuture<G, SynthesisError> >,
-    c: u32
+/// Same as `multiexp`, but blocks until the result is ready and adds it to
+/// `acc`, e.g. a term computed beforehand. `acc` is left as it was if the
+/// multiexp fails.
+pub fn multiexp_into<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    acc: &mut <G as CurveAffine>::Projective
+) -> Result<(), SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    multiexp(pool, bases, density_map, exponents).add_into(acc)
 }
 
-impl<G: CurveProjective> Future for ChunksJoiner<G> {
-    type Output = Result<G, SynthesisError>;
+/// Same as `multiexp`, but with the window narrowed until the buckets of
+/// the region tasks that run at the same time, one set of `2^c` per pool
+/// thread, fit into `max_bytes`. Every bit taken off the window halves the
+/// bucket memory, but adds regions and so passes over the bases: going from
+/// `c` to `c - k` bits costs roughly `c / (c - k)` times the bucketing time.
+/// The window never goes below one bit, even if that exceeds `max_bytes`.
+pub fn multiexp_with_mem_limit<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    max_bytes: usize
+) -> ChunksJoiner< <G as CurveAffine>::Projective >
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    let c = mem_limit_window_size::<G>(pool, exponents.len(), max_bytes);
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output>
-    {
-        let c = self.as_ref().c;
-        let join = unsafe { self.map_unchecked_mut(|s| &mut s.join) };
-        match join.poll(cx) {
-            Poll::Ready(v) => {
-                let v = join_chunks(v, c);
-                return Poll::Ready(v);
-            },
-            Poll::Pending => {
-                return Poll::Pending;
-            }
-        }
-    }
+    multiexp_with_window(pool, bases, density_map, exponents, c)
 }
 
-impl<G: CurveProjective> ChunksJoiner<G> {
-    pub fn wait(self) -> <Self as Future>::Output {
-        block_on(self)
+/// `window_size`, narrowed until `num_cpus` sets of `2^c` buckets take at
+/// most `max_bytes`, but to no less than one bit.
+fn mem_limit_window_size<G: CurveAffine>(pool: &Worker, num_exponents: usize, max_bytes: usize) -> u32 {
+    let bucket_size = std::mem::size_of::<<G as CurveAffine>::Projective>();
+    let bytes = |c: u32| pool.num_cpus().saturating_mul(1usize << c).saturating_mul(bucket_size);
+
+    let mut c = window_size(num_exponents);
+    while c > 1 && bytes(c) > max_bytes {
+        c -= 1;
     }
+
+    c
 }
 
-fn join_chunks<G: CurveProjective>
-    (chunks: Vec<Result<G, SynthesisError>>, c: u32) -> Result<G, SynthesisError> {
-    if chunks.len() == 0 {
-        return Ok(G::zero());
+/// Same as `multiexp`, but computed on the calling thread with no `Worker`
+/// at all, one region after the other. The window and the order in which
+/// regions are combined are the same, so the result is the same element.
+pub fn multiexp_serial<Q, D, G, S>(
+    bases: S,
+    density_map: D,
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    if let Some(query_size) = density_map.as_ref().get_query_size() {
+        check_lengths(query_size, exponents.len())?;
     }
 
-    let mut iter = chunks.into_iter().rev();
-    let higher = iter.next().expect("is some chunk result");
-    let mut higher = higher?;
+    let c = window_size(exponents.len());
+    let control = RegionControl {
+        cancelled: Arc::new(AtomicBool::new(false)),
+        progress: None
+    };
+    let regions = (0..<G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c))
+        .map(|region| multiexp_region(bases.clone(), &density_map, exponents, region * c, c, &control))
+        .collect::<Vec<_>>();
 
-    for chunk in iter {
-        let this = chunk?;
-        for _ in 0..c {
-            higher.double();
-        }
+    join_chunks(regions, c)
+}
 
-        higher.add_assign(&this);
+/// Same as `multiexp`, but the regions run on the global `rayon` pool, for
+/// callers that already use `rayon` and should not start a `Worker` pool
+/// next to it. The window, the regions and the order in which they are
+/// combined are the same as in `multiexp`, so the result is the same
+/// element. Mixing this with `Worker` based multiexps in one process is
+/// discouraged, as the two pools then compete for the same cores.
+#[cfg(feature = "rayon")]
+pub fn multiexp_rayon<Q, D, G, S>(
+    bases: S,
+    density_map: D,
+    exponents: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: Sync + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    use rayon::prelude::*;
+
+    if let Some(query_size) = density_map.as_ref().get_query_size() {
+        check_lengths(query_size, exponents.len())?;
     }
 
-    Ok(higher)
+    let c = window_size(exponents.len());
+    let control = RegionControl {
+        cancelled: Arc::new(AtomicBool::new(false)),
+        progress: None
+    };
+    let regions = (0..<G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c))
+        .into_par_iter()
+        .map(|region| catch_worker_panic(|| {
+            multiexp_region(bases.clone(), &density_map, exponents, region * c, c, &control)
+        }))
+        .collect::<Vec<_>>();
+
+    join_chunks(regions, c)
 }
 
+/// Parameters `multiexp_with_report` chose for a multiexp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiexpReport {
+    /// Window size in bits
+    pub c: u32,
+    /// Number of regions, each computed by its own task
+    pub num_chunks: usize,
+    /// Number of base and exponent pairs
+    pub num_bases: usize
+}
 
-/// Perform multi-exponentiation. The caller is responsible for ensuring that
-/// the number of bases is the same as the number of exponents.
-#[allow(dead_code)]
-pub fn dense_multiexp<G: CurveAffine>(
+/// Same as `multiexp`, but also reports the window size and the number of
+/// tasks it was split into.
+pub fn multiexp_with_report<Q, D, G, S>(
     pool: &Worker,
-    bases: & [G],
-    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
-) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+) -> (ChunksJoiner< <G as CurveAffine>::Projective >, MultiexpReport)
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
 {
-    if exponents.len() != bases.len() {
-        return Err(SynthesisError::AssignmentMissing);
-    }
-    let c = if exponents.len() < 32 {
-        3u32
-    } else {
-        (f64::from(exponents.len() as u32)).ln().ceil() as u32
+    let c = window_size(exponents.len());
+    let report = MultiexpReport {
+        c,
+        num_chunks: <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c) as usize,
+        num_bases: exponents.len()
     };
 
-    dense_multiexp_inner(pool, bases, exponents, 0, c, true)
+    (multiexp_with_window(pool, bases, density_map, exponents, c), report)
 }
 
-fn dense_multiexp_inner<G: CurveAffine>(
+/// Same as `multiexp`, but calls `progress` with the number of bases processed
+/// so far, after every `PROGRESS_STEP` bases of each region and at the end of
+/// every region. Without a callback no extra work is done per base.
+pub fn multiexp_with_progress<Q, D, G, S>(
     pool: &Worker,
-    bases: & [G],
-    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
-    mut skip: u32,
-    c: u32,
-    handle_trivial: bool
-) -> Result<<G as CurveAffine>::Projective, SynthesisError>
-{   
-    use std::sync::{Mutex};
-    // Perform this region of the multiexp. We use a different strategy - go over region in parallel,
-    // then over another region, etc. No Arc required
-    let this = {
-        // let mask = (1u64 << c) - 1u64;
-        let this_region = Mutex::new(<G as CurveAffine>::Projective::zero());
-        let arc = Arc::new(this_region);
-        pool.scope(bases.len(), |scope, chunk| {
-            for (base, exp) in bases.chunks(chunk).zip(exponents.chunks(chunk)) {
-                let this_region_rwlock = arc.clone();
-                // let handle = 
-                scope.spawn(move |_| {
-                    let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
-                    // Accumulate the result
-                    let mut acc = G::Projective::zero();
-                    let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
-                    let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
-
-                    for (base, &exp) in base.iter().zip(exp.iter()) {
-                        // let index = (exp.as_ref()[0] & mask) as usize;
-
-                        // if index != 0 {
-                        //     buckets[index - 1].add_assign_mixed(base);
-                        // }
-
-                        // exp.shr(c as u32);
-
-                        if exp != zero {
-                            if exp == one {
-                                if handle_trivial {
-                                    acc.add_assign_mixed(base);
-                                }
-                            } else {
-                                let mut exp = exp;
-                                exp.shr(skip);
-                                let exp = exp.as_ref()[0] % (1 << c);
-                                if exp != 0 {
-                                    buckets[(exp - 1) as usize].add_assign_mixed(base);
-                                }
-                            }
-                        }
-                    }
-
-                    // buckets are filled with the corresponding accumulated value, now sum
-                    let mut running_sum = G::Projective::zero();
-                    for exp in buckets.into_iter().rev() {
-                        running_sum.add_assign(&exp);
-                        acc.add_assign(&running_sum);
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    progress: ProgressCallback
+) -> ChunksJoiner< <G as CurveAffine>::Projective >
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    let c = window_size(exponents.len());
+
+    multiexp_lowest_regions(pool, bases, density_map, exponents, c, usize::MAX, Some(progress))
+}
+
+/// Perform multi-exponentiation over only the lowest `regions` windows.
+/// This is deliberately NOT the full multiexp: the contribution of the upper
+/// `c * regions..NUM_BITS` bits of every scalar is left out, which is only
+/// acceptable as a speculative pre-check when those bits are known to be
+/// small. Any `regions` at least the number of windows gives the exact result.
+pub fn multiexp_approx<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    regions: usize
+) -> ChunksJoiner< <G as CurveAffine>::Projective >
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    let c = window_size(exponents.len());
+
+    multiexp_lowest_regions(pool, bases, density_map, exponents, c, regions, None)
+}
+
+fn multiexp_lowest_regions<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>,
+    c: u32,
+    regions: usize,
+    progress: Option<ProgressCallback>
+) -> ChunksJoiner< <G as CurveAffine>::Projective >
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    let span = multiexp_span(exponents.len(), c);
+    let _enter = span.enter();
+
+    if let Some(query_size) = density_map.as_ref().get_query_size() {
+        // If the density map has a known query size, it should not be
+        // inconsistent with the number of exponents.
+
+        if let Err(err) = check_lengths(query_size, exponents.len()) {
+            return ChunksJoiner {
+                regions: vec![WorkerFuture::ready(Err(err))],
+                acc: None,
+                c,
+                cancelled: Arc::new(AtomicBool::new(false))
+            };
+        }
+    }
+
+    let num_regions = std::cmp::min(<G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c) as usize, regions);
+    let control = RegionControl {
+        cancelled: Arc::new(AtomicBool::new(false)),
+        progress: progress.map(|callback| Arc::new(Progress {
+            callback,
+            total: exponents.len() * num_regions,
+            processed: Mutex::new(0)
+        }))
+    };
+    let mut skip = 0;
+    let mut futures = Vec::with_capacity(num_regions);
+
+    while futures.len() < num_regions {
+        let chunk_future = multiexp_inner_impl(pool, bases.clone(), density_map.clone(), exponents.clone(), skip, c, control.clone());
+
+        futures.push(chunk_future);
+        skip += c;
+    }
+
+    ChunksJoiner {
+        regions: futures,
+        acc: None,
+        c,
+        cancelled: control.cancelled
+    }
+}
+
+/// Perform multi-exponentiation and return the result in canonical form,
+/// i.e. normalized so that `z = 1` (or the canonical identity). The value of
+/// a multiexp does not depend on the number of threads, but its projective
+/// coordinates do; this makes the raw representation deterministic too.
+pub fn multiexp_normalized<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    let result = multiexp(pool, bases, density_map, exponents).wait()?;
+
+    Ok(result.into_affine().into_projective())
+}
+
+/// Output form of `multiexp_out`, selected by one of the marker types
+/// `ProjectiveOutput` or `AffineOutput`.
+pub trait OutputForm<G: CurveAffine> {
+    type Output;
+
+    /// Convert a batch of results at once, so that affine outputs share a
+    /// single field inversion.
+    fn convert(points: Vec<<G as CurveAffine>::Projective>) -> Vec<Self::Output>;
+}
+
+/// Results stay in projective coordinates, ready for further additions.
+pub struct ProjectiveOutput;
+
+/// Results are batch normalized into affine points.
+pub struct AffineOutput;
+
+impl<G: CurveAffine> OutputForm<G> for ProjectiveOutput {
+    type Output = <G as CurveAffine>::Projective;
+
+    fn convert(points: Vec<<G as CurveAffine>::Projective>) -> Vec<Self::Output> {
+        points
+    }
+}
+
+impl<G: CurveAffine> OutputForm<G> for AffineOutput {
+    type Output = G;
+
+    fn convert(mut points: Vec<<G as CurveAffine>::Projective>) -> Vec<G> {
+        G::Projective::batch_normalization(&mut points);
+
+        points.into_iter().map(|p| p.into_affine()).collect()
+    }
+}
+
+/// Perform multi-exponentiation and return the result in the form `O`.
+pub fn multiexp_out<O, Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+) -> Result<O::Output, SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>,
+          O: OutputForm<G>
+{
+    let result = multiexp(pool, bases, density_map, exponents).wait()?;
+
+    Ok(O::convert(vec![result]).pop().expect("one result is converted"))
+}
+
+/// Perform multi-exponentiation and return the result in affine coordinates,
+/// which costs one field inversion. Same as `multiexp_out::<AffineOutput, ..>`.
+pub fn multiexp_affine<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+) -> Result<G, SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    multiexp_out::<AffineOutput, _, _, _, _>(pool, bases, density_map, exponents)
+}
+
+/// Same as `dense_multiexp`, but returns the result in affine coordinates,
+/// which costs one field inversion.
+pub fn dense_multiexp_affine<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<G, SynthesisError>
+{
+    Ok(dense_multiexp(pool, bases, exponents)?.into_affine())
+}
+
+/// Perform all the multi-exponentiations of `jobs` concurrently and return
+/// their results in order, in the form `O`.
+pub fn multiexp_out_batch<O, G, S>(
+    pool: &Worker,
+    jobs: Vec<MultiexpJob<G, S>>
+) -> Result<Vec<O::Output>, SynthesisError>
+    where G: CurveAffine,
+          S: SourceBuilder<G>,
+          O: OutputForm<G>
+{
+    let joiners = jobs.into_iter()
+        .map(|job| multiexp(pool, job.bases, FullDensity, job.exponents))
+        .collect::<Vec<_>>();
+
+    let mut results = Vec::with_capacity(joiners.len());
+    for joiner in joiners.into_iter() {
+        results.push(joiner.wait()?);
+    }
+
+    Ok(O::convert(results))
+}
+
+/// Perform multi-exponentiation and return only the affine x-coordinate of the
+/// result, for protocols that commit to it. The identity has no coordinates
+/// and is reported as `SynthesisError::UnexpectedIdentity`.
+pub fn multiexp_affine_x<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+) -> Result<<G::Base as PrimeField>::Repr, SynthesisError>
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          G::Base: PrimeField,
+          S: SourceBuilder<G>
+{
+    let result = multiexp(pool, bases, density_map, exponents).wait()?.into_affine();
+    if result.is_zero() {
+        return Err(SynthesisError::UnexpectedIdentity);
+    }
+
+    Ok(result.into_xy_unchecked().0.into_repr())
+}
+
+/// Result of `multiexp`, resolving once all the regions are done. Dropping it
+/// before then cancels the regions that are still running or queued.
+///
+/// Regions are folded into the result from the highest one down, in the same
+/// order as `join_chunks`, as soon as each of them and all the ones above it
+/// are done, rather than collecting every region first. An error resolves the
+/// joiner right away, with the same error `join_chunks` would pick.
+pub struct ChunksJoiner<G: CurveProjective> {
+    // Regions not folded yet, the highest one last
+    regions: Vec<WorkerFuture<G, SynthesisError>>,
+    // Regions folded so far, `None` until the highest one is done
+    acc: Option<G>,
+    c: u32,
+    cancelled: Arc<AtomicBool>
+}
+
+// Nothing in the joiner is ever pinned in place, the region futures are
+// `Unpin` and the accumulator is only moved in and out by value
+impl<G: CurveProjective> Unpin for ChunksJoiner<G> {}
+
+impl<G: CurveProjective> Future for ChunksJoiner<G> {
+    type Output = Result<G, SynthesisError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output>
+    {
+        let this = self.get_mut();
+
+        while let Some(region) = this.regions.last_mut() {
+            match Pin::new(region).poll(cx) {
+                Poll::Ready(Ok(region)) => {
+                    this.regions.pop();
+                    this.acc = Some(match this.acc.take() {
+                        Some(mut acc) => {
+                            for _ in 0..this.c {
+                                acc.double();
+                            }
+                            acc.add_assign(&region);
+
+                            acc
+                        },
+                        None => region
+                    });
+                },
+                Poll::Ready(Err(e)) => {
+                    this.regions.clear();
+                    return Poll::Ready(Err(e));
+                },
+                Poll::Pending => {
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        Poll::Ready(Ok(this.acc.take().unwrap_or_else(G::zero)))
+    }
+}
+
+impl<G: CurveProjective> ChunksJoiner<G> {
+    /// Block the calling thread until the result is ready. Do not call this
+    /// from a task running on a `Worker`: the futures executor panics when
+    /// entered from within the thread pool, and blocking every thread of the
+    /// worker this way would deadlock anyway, as the regions queued behind
+    /// them could never start. Use `wait_on` there.
+    pub fn wait(self) -> <Self as Future>::Output {
+        block_on(self)
+    }
+
+    /// Block until the result is ready, first running on the calling thread
+    /// the regions that `pool` has not started yet, so that it is safe to
+    /// call from within a task of `pool` itself.
+    pub fn wait_on(self, pool: &Worker) -> <Self as Future>::Output {
+        for region in self.regions.iter().rev() {
+            region.run_here();
+        }
+
+        pool.block_on(self)
+    }
+
+    /// Ask the regions still running or queued to stop. They bail out after
+    /// the base they are on, and the joiner resolves to
+    /// `SynthesisError::Cancelled` unless every region had already finished.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Block like `wait` and add the result to `acc`, which is left as it
+    /// was if the multiexp fails.
+    pub fn add_into(self, acc: &mut G) -> Result<(), SynthesisError> {
+        acc.add_assign(&self.wait()?);
+
+        Ok(())
+    }
+
+    /// Resolve to the result converted into affine coordinates, which costs
+    /// one field inversion.
+    pub fn into_affine(self) -> impl Future<Output = Result<G::Affine, SynthesisError>> {
+        self.map(|result| result.map(|p| p.into_affine()))
+    }
+}
+
+/// Reusable executor for threads that synchronously wait on many multiexps.
+/// `ChunksJoiner::wait` sets up a fresh executor on every call; holding one
+/// of these instead keeps that setup out of the per-call cost.
+pub struct MultiexpExecutor {
+    pool: LocalPool
+}
+
+impl MultiexpExecutor {
+    pub fn new() -> Self {
+        MultiexpExecutor {
+            pool: LocalPool::new()
+        }
+    }
+
+    /// Drive `future` to completion on the calling thread.
+    pub fn run<F: Future>(&mut self, future: F) -> F::Output {
+        self.pool.run_until(future)
+    }
+}
+
+impl Default for MultiexpExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G: CurveProjective> Drop for ChunksJoiner<G> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// One independent multi-exponentiation submitted to `multiexp_batch`.
+pub struct MultiexpJob<G: CurveAffine, S: SourceBuilder<G>> {
+    pub bases: S,
+    pub exponents: Arc<Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+}
+
+/// Start all `jobs` on the pool at once and yield `(job_index, result)` pairs
+/// in completion order, so that the first finished result can be consumed
+/// while the others are still being computed.
+pub fn multiexp_batch<G, S>(
+    pool: &Worker,
+    jobs: Vec<MultiexpJob<G, S>>
+) -> impl Stream<Item = (usize, Result<<G as CurveAffine>::Projective, SynthesisError>)>
+    where G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    jobs.into_iter()
+        .enumerate()
+        .map(|(idx, job)| multiexp(pool, job.bases, FullDensity, job.exponents).map(move |result| (idx, result)))
+        .collect::<FuturesUnordered<_>>()
+}
+
+/// Start one multiexp per exponent vector, all against the same bases and
+/// density map, and return their joiners in the order of `exponents`. The
+/// window is chosen once for the longest vector and shared by the batch.
+pub fn multiexp_batch_with_shared_bases<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    exponents: & [Arc<Vec<<G::Scalar as PrimeField>::Repr>>]
+) -> Vec<ChunksJoiner< <G as CurveAffine>::Projective >>
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    let c = window_size(exponents.iter().map(|e| e.len()).max().unwrap_or(0));
+
+    exponents.iter()
+        .map(|e| multiexp_with_window(pool, bases.clone(), density_map.clone(), e.clone(), c))
+        .collect()
+}
+
+fn join_chunks<G: CurveProjective>
+    (chunks: Vec<Result<G, SynthesisError>>, c: u32) -> Result<G, SynthesisError> {
+    if chunks.len() == 0 {
+        return Ok(G::zero());
+    }
+
+    // Every region starts `c` bits above the previous one, including the
+    // most significant one when it is narrower, so shifting by a full window
+    // is right for all of them.
+    let mut iter = chunks.into_iter().rev();
+    let higher = iter.next().expect("is some chunk result");
+    let mut higher = higher?;
+
+    for chunk in iter {
+        let this = chunk?;
+        for _ in 0..c {
+            higher.double();
+        }
+
+        higher.add_assign(&this);
+    }
+
+    Ok(higher)
+}
+
+/// Number of exponents that `streaming_multiexp` keeps in memory at once.
+pub const STREAMING_BATCH_SIZE: usize = 1 << 16;
+
+/// Perform multi-exponentiation of `len` exponents pulled from an iterator against
+/// the bases produced by `bases`, without materializing either of them. Exponents
+/// are consumed in batches of `STREAMING_BATCH_SIZE` and every batch is processed
+/// like a regular `multiexp`, so at most one batch of exponents plus the bucket
+/// sets of its regions are alive at any time, regardless of `len`. Memory stays
+/// bounded as long as the `SourceBuilder` itself streams its bases (e.g. from disk).
+pub fn streaming_multiexp<G, S, I>(
+    pool: &Worker,
+    bases: S,
+    exponents: I,
+    len: usize
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    where G: CurveAffine,
+          S: SourceBuilder<G>,
+          I: IntoIterator<Item = <<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>
+{
+    let mut exponents = exponents.into_iter();
+    let mut acc = G::Projective::zero();
+    let mut offset = 0;
+
+    while offset < len {
+        let batch_len = std::cmp::min(STREAMING_BATCH_SIZE, len - offset);
+        let batch = exponents.by_ref().take(batch_len).collect::<Vec<_>>();
+        if batch.len() != batch_len {
+            return Err(SynthesisError::LengthMismatch {
+                bases: len,
+                exponents: offset + batch.len()
+            });
+        }
+
+        let batch_bases = OffsetSourceBuilder {
+            inner: bases.clone(),
+            offset
+        };
+        let batch_result = multiexp(pool, batch_bases, FullDensity, Arc::new(batch)).wait()?;
+        acc.add_assign(&batch_result);
+
+        offset += batch_len;
+    }
+
+    Ok(acc)
+}
+
+/// `SynthesisError::LengthMismatch` unless there are as many bases as exponents.
+fn check_lengths(bases: usize, exponents: usize) -> Result<(), SynthesisError> {
+    if bases != exponents {
+        return Err(SynthesisError::LengthMismatch {
+            bases,
+            exponents
+        });
+    }
+
+    Ok(())
+}
+
+/// Perform multi-exponentiation. Fails with `SynthesisError::LengthMismatch`
+/// if the number of bases is not the same as the number of exponents.
+#[allow(dead_code)]
+pub fn dense_multiexp<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    let span = multiexp_span(exponents.len(), c);
+    let _enter = span.enter();
+
+    dense_multiexp_inner::<_, Limbs64>(pool, bases, exponents, 0, c, true)
+}
+
+/// Same as `multiexp` with full density, but borrowing the bases and the
+/// exponents instead of requiring `Arc`s, so callers holding a slice do not
+/// have to copy it. The work runs on scoped threads that are all joined
+/// before this returns. The window is the one `multiexp` picks, without the
+/// cache limit `dense_multiexp` applies. Fails with
+/// `SynthesisError::LengthMismatch` if the lengths differ.
+pub fn multiexp_slice<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = window_size(exponents.len());
+
+    let span = multiexp_span(exponents.len(), c);
+    let _enter = span.enter();
+
+    dense_multiexp_inner::<_, Limbs64>(pool, bases, exponents, 0, c, true)
+}
+
+/// Same as `dense_multiexp`, but pulls the exponents from an iterator, e.g.
+/// one decoding them from a memory mapped file. Only `STREAMING_BATCH_SIZE`
+/// exponents are held at once; every batch is split over the worker scope
+/// like in `dense_multiexp`, with the window chosen up front for the whole
+/// length. Fails with `SynthesisError::LengthMismatch` if the iterator does
+/// not yield exactly one exponent per base.
+pub fn dense_multiexp_iter<G, I>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: I
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    where G: CurveAffine,
+          I: ExactSizeIterator<Item = <<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    let mut exponents = exponents;
+    let mut acc = G::Projective::zero();
+    for (i, bases) in bases.chunks(STREAMING_BATCH_SIZE).enumerate() {
+        let batch = exponents.by_ref().take(bases.len()).collect::<Vec<_>>();
+        if batch.len() != bases.len() {
+            return Err(SynthesisError::LengthMismatch {
+                bases: (i * STREAMING_BATCH_SIZE) + bases.len(),
+                exponents: (i * STREAMING_BATCH_SIZE) + batch.len()
+            });
+        }
+
+        acc.add_assign(&dense_multiexp_inner::<_, Limbs64>(pool, bases, &batch, 0, c, true)?);
+    }
+
+    Ok(acc)
+}
+
+/// `window_size`, lowered if needed so that the buckets of one chunk fit in
+/// the L2 cache of the worker. The bases and exponents are streamed through
+/// once per region, so the buckets are the part of the working set that is
+/// accessed at random.
+pub(crate) fn cache_window_size<G: CurveAffine>(pool: &Worker, num_exponents: usize) -> u32 {
+    let c = window_size(num_exponents);
+
+    match pool.cache_sizes().l2 {
+        Some(l2) => {
+            let max_buckets = l2 / std::mem::size_of::<<G as CurveAffine>::Projective>();
+            // `2^c - 1` buckets fit as long as `2^c <= max_buckets + 1`
+            let max_c = (max_buckets + 1).checked_ilog2().unwrap_or(0);
+
+            std::cmp::max(std::cmp::min(c, max_c), 1)
+        },
+        None => c
+    }
+}
+
+/// Default bound on the number of regions `dense_multiexp_with_window` accepts,
+/// enough for a 1-bit window over any of the supported scalar fields.
+pub const DEFAULT_MAX_REGIONS: u32 = 256;
+
+/// Widest window `dense_multiexp_with_window` accepts, `2^c - 1` buckets are
+/// allocated for every region in flight.
+pub const MAX_WINDOW_SIZE: u32 = 24;
+
+/// Same as `dense_multiexp`, but with a window of `c` bits chosen by the caller.
+/// Regions are processed recursively, so `c` must split the scalar into at most
+/// `max_regions` of them; `c = 0`, windows wider than `MAX_WINDOW_SIZE` or ones
+/// producing too many regions are rejected with `SynthesisError::InvalidWindow`.
+pub fn dense_multiexp_with_window<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    c: u32,
+    max_regions: u32
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    if c == 0 || c > MAX_WINDOW_SIZE || <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c) > max_regions {
+        return Err(SynthesisError::InvalidWindow(c));
+    }
+    match check_window(exponents.len(), c) {
+        WindowAdvice::Reasonable => {},
+        advice => elog_verbose!("multiexp window of {} bits for {} exponents: {:?}", c, exponents.len(), advice)
+    }
+
+    dense_multiexp_inner::<_, Limbs64>(pool, bases, exponents, 0, c, true)
+}
+
+/// Verdict of `check_window` on a window size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowAdvice {
+    /// Within the tolerance of the best window
+    Reasonable,
+    /// So many buckets that summing them dominates the bucket filling
+    TooLarge { suggested: u32 },
+    /// So few buckets that there are needlessly many regions
+    TooSmall { suggested: u32 }
+}
+
+/// Estimated cost of a window of `c` bits per scalar bit: every region costs one
+/// addition per pair to fill the buckets and two per bucket to sum them up.
+fn window_cost(len: usize, c: u32) -> f64 {
+    (len as f64 + (1u64 << (c + 1)) as f64) / f64::from(c)
+}
+
+/// Check whether a window of `c` bits suits a multiexp of `len` pairs. Windows
+/// estimated to cost over 1.5 times the best one up to `MAX_WINDOW_SIZE` are
+/// flagged with that best one as a suggestion.
+pub fn check_window(len: usize, c: u32) -> WindowAdvice {
+    let suggested = (1..=MAX_WINDOW_SIZE)
+        .min_by(|a, b| window_cost(len, *a).partial_cmp(&window_cost(len, *b)).expect("costs are finite"))
+        .expect("is some window");
+
+    if c != 0 && window_cost(len, c) <= 1.5 * window_cost(len, suggested) {
+        WindowAdvice::Reasonable
+    } else if c > suggested {
+        WindowAdvice::TooLarge { suggested }
+    } else {
+        WindowAdvice::TooSmall { suggested }
+    }
+}
+
+/// Same as `dense_multiexp`, but first scans the exponents in parallel and, when
+/// at most one of them is nonzero (e.g. a one-hot selector), returns a single
+/// scalar multiplication instead of running the bucket method.
+pub fn dense_multiexp_detect_sparse<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+
+    // per chunk: number of nonzero exponents (saturating at 2) and the last one seen
+    let chunk = pool.get_chunk_size(exponents.len());
+    let mut nonzero = vec![(0usize, 0usize); exponents.chunks(chunk).len()];
+
+    pool.scope(exponents.len(), |scope, chunk| {
+        for (i, (exp, found)) in exponents.chunks(chunk).zip(nonzero.iter_mut()).enumerate() {
+            scope.spawn(move |_| {
+                for (j, exp) in exp.iter().enumerate() {
+                    if !exp.is_zero() {
+                        *found = (found.0 + 1, i * chunk + j);
+                        if found.0 > 1 {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let mut count = 0;
+    let mut position = 0;
+    for &(found, idx) in nonzero.iter() {
+        if found != 0 {
+            count += found;
+            position = idx;
+        }
+    }
+
+    match count {
+        0 => Ok(G::Projective::zero()),
+        1 => Ok(bases[position].mul(exponents[position])),
+        _ => dense_multiexp(pool, bases, exponents)
+    }
+}
+
+/// Something a long running multiexp can poll to learn that its result is no
+/// longer needed, like a flag or an application wide cancellation token.
+pub trait Cancellation {
+    fn is_cancelled(&self) -> bool;
+}
+
+impl Cancellation for AtomicBool {
+    fn is_cancelled(&self) -> bool {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: Cancellation + ?Sized> Cancellation for Arc<T> {
+    fn is_cancelled(&self) -> bool {
+        (**self).is_cancelled()
+    }
+}
+
+#[cfg(feature = "tokio-cancellation")]
+impl Cancellation for tokio_util::sync::CancellationToken {
+    fn is_cancelled(&self) -> bool {
+        tokio_util::sync::CancellationToken::is_cancelled(self)
+    }
+}
+
+/// Same as `dense_multiexp`, but gives up with `SynthesisError::Cancelled` once
+/// `cancellation` reports it. It is checked up front and by every chunk
+/// before it starts, so work stops within one chunk of a region.
+pub fn dense_multiexp_cancellable<G: CurveAffine, C: Cancellation + Sync + ?Sized>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    cancellation: &C
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+    if cancellation.is_cancelled() {
+        return Err(SynthesisError::Cancelled);
+    }
+
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c), |region, range| {
+        if cancellation.is_cancelled() {
+            return None;
+        }
+        let skip = region * c;
+
+        Some(dense_multiexp_chunk::<_, Limbs64>(&bases[range.clone()], &exponents[range], skip, c, skip == 0))
+    }, |_, partials| {
+        let partials = partials.into_iter().collect::<Option<Vec<_>>>().ok_or(SynthesisError::Cancelled)?;
+        add_region(&mut acc, c, &partials);
+
+        Ok(())
+    })?;
+
+    Ok(acc)
+}
+
+/// Same as `dense_multiexp`, but also measures how well the work parallelized.
+/// Every chunk is timed; the sum of the chunk times estimates the serial time,
+/// and the returned efficiency is the achieved speedup over it divided by the
+/// number of chunks running side by side, so `1.0` means perfect scaling.
+/// Low figures usually mean the run is memory bound and more cores won't help.
+pub fn dense_multiexp_with_efficiency<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G as CurveAffine>::Projective, f64), SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    let start = Instant::now();
+    let chunk = pool.get_chunk_size(bases.len());
+    let width = bases.chunks(chunk).len();
+    let mut serial = Duration::default();
+
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c), |region, range| {
+        let chunk_start = Instant::now();
+        let skip = region * c;
+        let result = dense_multiexp_chunk::<_, Limbs64>(&bases[range.clone()], &exponents[range], skip, c, skip == 0);
+
+        (result, chunk_start.elapsed())
+    }, |_, partials| {
+        let (partials, elapsed): (Vec<_>, Vec<_>) = partials.into_iter().unzip();
+        add_region(&mut acc, c, &partials);
+        serial += elapsed.into_iter().sum::<Duration>();
+
+        Ok(())
+    })?;
+
+    let wall = start.elapsed().as_secs_f64();
+    let efficiency = if width == 0 || wall == 0f64 {
+        1f64
+    } else {
+        serial.as_secs_f64() / wall / (width as f64)
+    };
+
+    Ok((acc, efficiency))
+}
+
+/// Read `len` uncompressed bases and `len` big endian scalar representations
+/// from two separate readers, decode them in parallel and multiexp them. Both
+/// readers are drained concurrently; decoding errors, truncated input, scalars
+/// outside of the field and bases at infinity are reported as `SynthesisError`.
+pub fn multiexp_from_readers<G: CurveAffine, B: Read + Send, S: Read + Send>(
+    pool: &Worker,
+    mut bases_reader: B,
+    mut scalars_reader: S,
+    len: usize
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    let base_size = G::Uncompressed::size();
+    let scalar_size = <<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr::default().as_ref().len() * 8;
+
+    let too_long = || io::Error::new(io::ErrorKind::InvalidInput, "number of bases is too large");
+    let base_len = len.checked_mul(base_size).ok_or_else(too_long)?;
+    let scalar_len = len.checked_mul(scalar_size).ok_or_else(too_long)?;
+
+    let mut base_bytes = vec![];
+    let mut scalar_bytes = vec![];
+    let mut read_bases = Ok(());
+    let mut read_scalars = Ok(());
+
+    pool.scope(2, |scope, _| {
+        let (base_bytes, read_bases) = (&mut base_bytes, &mut read_bases);
+        let (scalar_bytes, read_scalars) = (&mut scalar_bytes, &mut read_scalars);
+        scope.spawn(move |_| *read_bases = read_up_to(&mut bases_reader, base_len, base_bytes));
+        scope.spawn(move |_| *read_scalars = read_up_to(&mut scalars_reader, scalar_len, scalar_bytes));
+    });
+    read_bases?;
+    read_scalars?;
+
+    let mut bases = vec![G::zero(); len];
+    let mut exponents = vec![<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr::default(); len];
+    let chunk = pool.get_chunk_size(len);
+    let mut results = (0..bases.chunks(chunk).len()).map(|_| Ok(())).collect::<Vec<_>>();
+
+    pool.scope(len, |scope, chunk| {
+        for ((((bases, exponents), base_bytes), scalar_bytes), result) in bases.chunks_mut(chunk)
+                    .zip(exponents.chunks_mut(chunk))
+                    .zip(base_bytes.chunks(chunk * base_size))
+                    .zip(scalar_bytes.chunks(chunk * scalar_size))
+                    .zip(results.iter_mut()) {
+            scope.spawn(move |_| {
+                *result = (|| -> Result<(), SynthesisError> {
+                    let mut encoded = G::Uncompressed::empty();
+                    for (base, bytes) in bases.iter_mut().zip(base_bytes.chunks(base_size)) {
+                        encoded.as_mut().copy_from_slice(bytes);
+                        *base = encoded.into_affine()
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                        if base.is_zero() {
+                            return Err(SynthesisError::UnexpectedIdentity);
+                        }
+                    }
+
+                    for (exp, bytes) in exponents.iter_mut().zip(scalar_bytes.chunks(scalar_size)) {
+                        exp.read_be(bytes)?;
+                        <G::Engine as ScalarEngine>::Fr::from_repr(*exp)
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    }
+
+                    Ok(())
+                })();
+            });
+        }
+    });
+
+    for result in results.into_iter() {
+        result?;
+    }
+
+    dense_multiexp(pool, &bases, &exponents)
+}
+
+/// Read exactly `len` bytes from `reader` into `buf`. The buffer grows with
+/// the data actually read, so a `len` far beyond the end of a truncated
+/// stream does not allocate it all up front.
+fn read_up_to<R: Read>(reader: &mut R, len: usize, buf: &mut Vec<u8>) -> io::Result<()> {
+    buf.reserve(std::cmp::min(len, 1 << 20));
+    reader.take(len as u64).read_to_end(buf)?;
+    if buf.len() != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "reader ended before all the bases and scalars were read"));
+    }
+
+    Ok(())
+}
+
+/// Decode little endian scalar representations, one per `8 * limbs` bytes of
+/// `bytes`, e.g. 32 bytes per scalar for BN256, ready to be passed to
+/// `multiexp`. Input that is not a whole number of scalars and encodings of
+/// values at or above the modulus are rejected as invalid data.
+pub fn scalars_from_le_bytes<E: Engine>(
+    bytes: &[u8]
+) -> Result<Vec<<E::Fr as PrimeField>::Repr>, SynthesisError>
+{
+    scalars_from_bytes::<E, _>(bytes, |repr, bytes| repr.read_le(bytes))
+}
+
+/// Same as `scalars_from_le_bytes`, for big endian scalars.
+pub fn scalars_from_be_bytes<E: Engine>(
+    bytes: &[u8]
+) -> Result<Vec<<E::Fr as PrimeField>::Repr>, SynthesisError>
+{
+    scalars_from_bytes::<E, _>(bytes, |repr, bytes| repr.read_be(bytes))
+}
+
+fn scalars_from_bytes<E, R>(
+    bytes: &[u8],
+    read: R
+) -> Result<Vec<<E::Fr as PrimeField>::Repr>, SynthesisError>
+    where E: Engine,
+          R: Fn(&mut <E::Fr as PrimeField>::Repr, &[u8]) -> io::Result<()>
+{
+    let scalar_size = <E::Fr as PrimeField>::Repr::default().as_ref().len() * 8;
+    if !bytes.len().is_multiple_of(scalar_size) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} bytes are not a whole number of {} byte scalars", bytes.len(), scalar_size)
+        ).into());
+    }
+
+    bytes.chunks(scalar_size).enumerate().map(|(i, bytes)| {
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        read(&mut repr, bytes)?;
+        if repr >= E::Fr::char() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("scalar {} is not below the modulus", i)
+            ).into());
+        }
+
+        Ok(repr)
+    }).collect()
+}
+
+/// Signed digit of `exp` for the window of `c` bits at `skip`, in
+/// `[-2^(c-1), 2^(c-1)]`. A window whose top bit is set is taken as
+/// `window - 2^c` and carries one into the next window, which reads that
+/// carry back as the bit right below it, so every digit is found locally.
+#[cfg(feature = "signed-buckets")]
+fn signed_window<R: PrimeFieldRepr>(exp: &R, skip: u32, c: u32) -> i64 {
+    let window = Limbs64::window(exp, skip, c) as i64;
+    let carry_in = if skip == 0 { 0 } else { Limbs64::window(exp, skip - 1, 1) as i64 };
+    let carry_out = window >> (c - 1);
+
+    window + carry_in - (carry_out << c)
+}
+
+/// Same as `dense_multiexp`, but with signed window digits, so that only the
+/// `2^(c-1)` buckets for digits of magnitude `1..=2^(c-1)` are needed: a base
+/// with a negative digit is negated, which is nearly free, before going into
+/// its bucket. This halves the bucket summation for the price of one extra
+/// region to absorb the final carry. Scalars must be canonical.
+#[cfg(feature = "signed-buckets")]
+pub fn dense_multiexp_signed<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    // One bit wider windows cost the same summation as unsigned ones
+    let c = cache_window_size::<G>(pool, exponents.len()) + 1;
+    // The top bit of the top window must be clear so that nothing carries out
+    let num_regions = (<G::Engine as ScalarEngine>::Fr::NUM_BITS + 1).div_ceil(c);
+
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), num_regions, |region, range| {
+        let skip = region * c;
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); 1 << (c - 1)];
+        for (base, exp) in bases[range.clone()].iter().zip(exponents[range].iter()) {
+            let digit = signed_window(exp, skip, c);
+            if digit > 0 {
+                buckets[(digit - 1) as usize].add_assign_mixed(base);
+            } else if digit < 0 {
+                let mut negated = *base;
+                negated.negate();
+                buckets[(-digit - 1) as usize].add_assign_mixed(&negated);
+            }
+        }
+
+        serial_sum_buckets(&buckets)
+    }, |_, partials| {
+        add_region(&mut acc, c, &partials);
+
+        Ok(())
+    })?;
+
+    Ok(acc)
+}
+
+/// Bucket arrays kept between multiexps, so that back-to-back calls reset the
+/// buckets instead of allocating them again. Every chunk of a region takes
+/// one array and puts it back when done, so the scratch ends up holding as
+/// many arrays as chunks run at once.
+pub struct BucketScratch<P: CurveProjective> {
+    free: Mutex<Vec<Vec<P>>>
+}
+
+impl<P: CurveProjective> BucketScratch<P> {
+    pub fn new() -> Self {
+        BucketScratch {
+            free: Mutex::new(vec![])
+        }
+    }
+
+    /// Take an array of `len` buckets, all set to the identity.
+    fn take(&self, len: usize) -> Vec<P> {
+        let buckets = self.free.lock().ok().and_then(|mut free| free.pop());
+        match buckets {
+            Some(mut buckets) => {
+                buckets.clear();
+                buckets.resize(len, P::zero());
+                buckets
+            },
+            None => vec![P::zero(); len]
+        }
+    }
+
+    fn put_back(&self, buckets: Vec<P>) {
+        if let Ok(mut free) = self.free.lock() {
+            free.push(buckets);
+        }
+    }
+}
+
+impl<P: CurveProjective> Default for BucketScratch<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Same as `dense_multiexp`, but takes the bucket arrays from `scratch`
+/// rather than allocating them for every chunk of every region.
+pub fn dense_multiexp_with_scratch<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    scratch: &BucketScratch<<G as CurveAffine>::Projective>
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c), |region, range| {
+        let skip = region * c;
+        let mut buckets = scratch.take((1 << c) - 1);
+        let result = dense_multiexp_chunk_with_buckets::<_, Limbs64>(&bases[range.clone()], &exponents[range], skip, c, skip == 0, &mut buckets);
+        scratch.put_back(buckets);
+
+        result
+    }, |_, partials| {
+        add_region(&mut acc, c, &partials);
+
+        Ok(())
+    })?;
+
+    Ok(acc)
+}
+
+/// Reduce a scalar representation modulo the field characteristic. Canonical
+/// representations are returned as they are; the rest are rebuilt bit by bit
+/// in the field, which is correct however many multiples of the modulus they
+/// exceed it by.
+fn reduce_scalar<F: PrimeField>(repr: F::Repr) -> F::Repr {
+    if repr < F::char() {
+        return repr;
+    }
+
+    let mut acc = F::zero();
+    for limb in repr.as_ref().iter().rev() {
+        for bit in (0..64).rev() {
+            acc.double();
+            if (limb >> bit) & 1 == 1 {
+                acc.add_assign(&F::one());
+            }
+        }
+    }
+
+    acc.into_repr()
+}
+
+/// Same as `dense_multiexp`, but first reduces every exponent that is not a
+/// canonical field element (equal to the group order or above it) modulo the
+/// group order, instead of bucketing its raw bits, which would give a result
+/// off by a multiple of the order times the base for the truncated bits.
+pub fn multiexp_reduce_scalars<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+
+    let mut reduced = exponents.to_vec();
+    pool.scope(reduced.len(), |scope, chunk| {
+        for exp in reduced.chunks_mut(chunk) {
+            scope.spawn(move |_| {
+                for exp in exp.iter_mut() {
+                    *exp = reduce_scalar::<<G::Engine as ScalarEngine>::Fr>(*exp);
+                }
+            });
+        }
+    });
+
+    dense_multiexp(pool, bases, &reduced)
+}
+
+/// Perform multi-exponentiation with scalars that are all 0 or 1, given as
+/// `bits`: this is just the sum of the bases whose bit is set, so no buckets
+/// or regions are involved.
+pub fn multiexp_boolean<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    bits: & [bool]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), bits.len())?;
+
+    let chunk = pool.get_chunk_size(bases.len());
+    let mut partials = vec![<G as CurveAffine>::Projective::zero(); bases.chunks(chunk).len()];
+
+    pool.scope(bases.len(), |scope, chunk| {
+        for ((base, bit), partial) in bases.chunks(chunk)
+                    .zip(bits.chunks(chunk))
+                    .zip(partials.iter_mut()) {
+            scope.spawn(move |_| {
+                for (base, _) in base.iter().zip(bit.iter()).filter(|(_, bit)| **bit) {
+                    partial.add_assign_mixed(base);
+                }
+            });
+        }
+    });
+
+    let mut acc = G::Projective::zero();
+    for partial in partials.iter() {
+        acc.add_assign(partial);
+    }
+
+    Ok(acc)
+}
+
+/// Perform multi-exponentiation with a sparse scalar vector given as
+/// `(index, scalar)` pairs, all other scalars being zero. Only the listed
+/// bases are touched, so the cost depends on `nonzero.len()` rather than on
+/// `bases.len()`. Indices may repeat, their scalars then add up.
+pub fn multiexp_sparse<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    nonzero: & [(usize, <<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr)]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    let mut picked = Vec::with_capacity(nonzero.len());
+    let mut exponents = Vec::with_capacity(nonzero.len());
+    for &(idx, exp) in nonzero.iter() {
+        let base = bases.get(idx).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "sparse scalar index is past the end of the bases")
+        })?;
+        picked.push(*base);
+        exponents.push(exp);
+    }
+
+    dense_multiexp(pool, &picked, &exponents)
+}
+
+/// Same as `dense_multiexp`, but drops the pairs whose base is the point at
+/// infinity before bucketing, as if their exponents were zero. The result is
+/// the same, without spending additions on them. `multiexp` sources reject
+/// such bases with `SynthesisError::UnexpectedIdentity` instead.
+pub fn multiexp_skip_identity_bases<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    if !bases.iter().any(|b| b.is_zero()) {
+        return dense_multiexp(pool, bases, exponents);
+    }
+
+    let (bases, exponents): (Vec<G>, Vec<_>) = bases.iter()
+        .zip(exponents.iter())
+        .filter(|(base, _)| !base.is_zero())
+        .unzip();
+
+    dense_multiexp(pool, &bases, &exponents)
+}
+
+/// Perform multi-exponentiation split across several workers, e.g. one per
+/// socket. Every worker gets a contiguous range of the pairs and runs
+/// `dense_multiexp` on it with its own threads, and the partial results are
+/// added up. Without any worker given, a default `Worker` does all the work.
+pub fn multiexp_multi_worker<G: CurveAffine>(
+    workers: & [Worker],
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    if workers.is_empty() {
+        return dense_multiexp(&Worker::new(), bases, exponents);
+    }
+
+    let chunk = std::cmp::max(bases.len().div_ceil(workers.len()), 1);
+
+    std::thread::scope(|scope| {
+        let handles = workers.iter()
+            .zip(bases.chunks(chunk).zip(exponents.chunks(chunk)))
+            .map(|(worker, (base, exp))| scope.spawn(move || dense_multiexp(worker, base, exp)))
+            .collect::<Vec<_>>();
+
+        let mut acc = G::Projective::zero();
+        for handle in handles {
+            let partial = handle.join().map_err(|_| SynthesisError::WorkerFailure)??;
+            acc.add_assign(&partial);
+        }
+
+        Ok(acc)
+    })
+}
+
+/// Perform multi-exponentiation and return both the result and its negation.
+pub fn multiexp_and_negation<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G as CurveAffine>::Projective, <G as CurveAffine>::Projective), SynthesisError>
+{
+    let result = dense_multiexp(pool, bases, exponents)?;
+    let mut negated = result;
+    negated.negate();
+
+    Ok((result, negated))
+}
+
+/// Multi-exponentiation future that borrows its inputs instead of requiring
+/// `Arc`s. Handing borrowed data to pool threads from a future is unsound,
+/// since a future may be leaked with the threads still running, so the work
+/// is not started in the background: the first poll runs `dense_multiexp`,
+/// whose scoped threads are all joined before the poll returns. The calling
+/// task is blocked for that long, so use `multiexp` where overlapping the
+/// computation with other work matters.
+pub fn multiexp_scoped<'a, G: CurveAffine>(
+    pool: &'a Worker,
+    bases: &'a [G],
+    exponents: &'a [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> impl Future<Output = Result<<G as CurveAffine>::Projective, SynthesisError>> + 'a
+{
+    lazy(move |_| dense_multiexp(pool, bases, exponents))
+}
+
+/// Check that every base is on the curve, e.g. after building them from raw
+/// bytes or limbs without validation. Only the curve equation is checked,
+/// not subgroup membership. Fails with the index of the first invalid base.
+pub fn validate_bases<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G]
+) -> Result<(), SynthesisError>
+{
+    let chunk = pool.get_chunk_size(bases.len());
+    let mut invalid = vec![None; bases.chunks(chunk).len()];
+
+    pool.scope(bases.len(), |scope, chunk| {
+        for ((i, base), invalid) in bases.chunks(chunk).enumerate().zip(invalid.iter_mut()) {
+            scope.spawn(move |_| {
+                *invalid = base.iter().position(|b| {
+                    if b.is_zero() {
+                        return false;
+                    }
+                    let (x, y) = b.into_xy_unchecked();
+                    G::from_xy_checked(x, y).is_err()
+                }).map(|pos| i * chunk + pos);
+            });
+        }
+    });
+
+    match invalid.into_iter().flatten().next() {
+        Some(idx) => Err(SynthesisError::InvalidBase(idx)),
+        None => Ok(())
+    }
+}
+
+/// Same as `dense_multiexp`, but runs `validate_bases` first.
+pub fn dense_multiexp_checked<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    validate_bases(pool, bases)?;
+
+    dense_multiexp(pool, bases, exponents)
+}
+
+/// Check that every base is in the prime order subgroup by multiplying it by
+/// the group order, which costs about a scalar multiplication per base. This
+/// only makes sense for bases already known to be on the curve. Fails with
+/// the index of the first base outside of the subgroup.
+pub fn validate_subgroup<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G]
+) -> Result<(), SynthesisError>
+{
+    let chunk = pool.get_chunk_size(bases.len());
+    let mut invalid = vec![None; bases.chunks(chunk).len()];
+
+    pool.scope(bases.len(), |scope, chunk| {
+        for ((i, base), invalid) in bases.chunks(chunk).enumerate().zip(invalid.iter_mut()) {
+            scope.spawn(move |_| {
+                *invalid = base.iter().position(|b| {
+                    !CurveProjective::is_zero(&b.mul(G::Scalar::char()))
+                }).map(|pos| i * chunk + pos);
+            });
+        }
+    });
+
+    match invalid.into_iter().flatten().next() {
+        Some(idx) => Err(SynthesisError::InvalidSubgroup(idx)),
+        None => Ok(())
+    }
+}
+
+/// Same as `dense_multiexp_checked`, additionally running `validate_subgroup`
+/// when `check_subgroup` is set, for bases that may come from an untrusted
+/// source such as a malicious CRS. Plain `dense_multiexp` trusts its bases.
+pub fn multiexp_checked<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    check_subgroup: bool
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    validate_bases(pool, bases)?;
+    if check_subgroup {
+        validate_subgroup(pool, bases)?;
+    }
+
+    dense_multiexp(pool, bases, exponents)
+}
+
+/// Time spent in the phases of one region of `dense_multiexp_with_stats`.
+/// Filling and summation are summed over all the chunks of the region, so
+/// they are CPU time rather than wall clock time.
+#[cfg(feature = "profile")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MultiexpStats {
+    /// Sorting the bases into buckets
+    pub fill_time: Duration,
+    /// Summation by parts over the buckets
+    pub sum_time: Duration,
+    /// Adding the chunk results and shifting the accumulator by the window
+    pub combine_time: Duration
+}
+
+/// Same as `dense_multiexp`, but separately times the bucket filling,
+/// bucket summation and combination phases of every region. Stats are
+/// returned from the lowest region up.
+#[cfg(feature = "profile")]
+pub fn dense_multiexp_with_stats<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G as CurveAffine>::Projective, Vec<MultiexpStats>), SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+    let num_regions = <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c);
+    let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
+
+    let mut stats = vec![MultiexpStats::default(); num_regions as usize];
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), num_regions, |region, range| {
+        let skip = region * c;
+        let start = Instant::now();
+        let mut acc = G::Projective::zero();
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+        for (base, exp) in bases[range.clone()].iter().zip(exponents[range].iter()) {
+            if *exp == one {
+                if skip == 0 {
+                    acc.add_assign_mixed(base);
+                }
+            } else {
+                let digit = Limbs64::window(exp, skip, c);
+                if digit != 0 {
+                    buckets[(digit - 1) as usize].add_assign_mixed(base);
+                }
+            }
+        }
+        let fill_time = start.elapsed();
+
+        let start = Instant::now();
+        acc.add_assign(&serial_sum_buckets(&buckets));
+
+        (acc, fill_time, start.elapsed())
+    }, |region, partials| {
+        let start = Instant::now();
+        let region_stats = &mut stats[region as usize];
+        let mut sums = Vec::with_capacity(partials.len());
+        for (partial, fill_time, sum_time) in partials.into_iter() {
+            sums.push(partial);
+            region_stats.fill_time += fill_time;
+            region_stats.sum_time += sum_time;
+        }
+        add_region(&mut acc, c, &sums);
+        region_stats.combine_time = start.elapsed();
+
+        Ok(())
+    })?;
+
+    Ok((acc, stats))
+}
+
+/// Same as `dense_multiexp`, but records the wall clock time every chunk of
+/// the worker took, to see how evenly the static split spreads the work.
+/// There is one entry per region, from the lowest one up, holding the time
+/// of every chunk of that region in the order of the bases.
+#[cfg(feature = "profile")]
+pub fn dense_multiexp_with_chunk_times<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G as CurveAffine>::Projective, Vec<Vec<Duration>>), SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+    let num_bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS;
+    let num_regions = num_bits.div_ceil(c);
+
+    let mut times = vec![vec![]; num_regions as usize];
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), num_regions, |region, range| {
+        let skip = region * c;
+        let width = std::cmp::min(c, num_bits - skip);
+        let start = Instant::now();
+        let result = dense_multiexp_chunk::<_, Limbs64>(&bases[range.clone()], &exponents[range], skip, width, skip == 0);
+
+        (result, start.elapsed())
+    }, |region, partials| {
+        let (partials, region_times): (Vec<_>, Vec<_>) = partials.into_iter().unzip();
+        add_region(&mut acc, c, &partials);
+        times[region as usize] = region_times;
+
+        Ok(())
+    })?;
+
+    Ok((acc, times))
+}
+
+/// Widest window `adaptive_schedule` considers.
+const MAX_ADAPTIVE_WINDOW: u32 = 16;
+
+/// Choose a non-uniform split of the `num_bits` scalar bits into windows,
+/// returned as `(skip, c)` pairs from the lowest one. Set bit frequencies are
+/// measured on a sample of the exponents; a window then costs one addition per
+/// exponent with a nonzero digit in it (assuming independent bits), two per
+/// bucket expected to be hit for the summation, one doubling per bit, and a
+/// pass over the exponents. Sparse windows, like the top ones of scalars
+/// reduced modulo the group order, come out wider. Dense windows stay within
+/// one bit of `window_size`, as wider bucket arrays stop fitting in cache.
+fn adaptive_schedule<R: PrimeFieldRepr>(exponents: &[R], num_bits: u32) -> Vec<(u32, u32)> {
+    const SAMPLE_SIZE: usize = 1 << 12;
+
+    let stride = std::cmp::max(exponents.len() / SAMPLE_SIZE, 1);
+    let mut set_bits = vec![0usize; num_bits as usize];
+    let mut sampled = 0;
+    for exp in exponents.iter().step_by(stride) {
+        for (bit, count) in set_bits.iter_mut().enumerate() {
+            if Limbs64::window(exp, bit as u32, 1) != 0 {
+                *count += 1;
+            }
+        }
+        sampled += 1;
+    }
+
+    let zero_probability = set_bits.iter()
+        .map(|&count| 1f64 - (count as f64) / (std::cmp::max(sampled, 1) as f64))
+        .collect::<Vec<_>>();
+    let n = exponents.len() as f64;
+    let max_dense_window = std::cmp::min(window_size(exponents.len()) + 1, MAX_ADAPTIVE_WINDOW);
+
+    // best[b] is the cost of the bits from `b` up, choice[b] the width of the window at `b`
+    let mut best = vec![0f64; num_bits as usize + 1];
+    let mut choice = vec![0u32; num_bits as usize];
+    for start in (0..num_bits).rev() {
+        let mut all_zero = 1f64;
+        best[start as usize] = f64::INFINITY;
+        for c in 1..=MAX_ADAPTIVE_WINDOW {
+            let end = std::cmp::min(start + c, num_bits);
+            if start + c <= num_bits {
+                all_zero *= zero_probability[(start + c - 1) as usize];
+            }
+            let additions = n * (1f64 - all_zero);
+            if c > max_dense_window && additions >= 1f64 {
+                break;
+            }
+            let buckets = (1u64 << c) as f64;
+            let used_buckets = buckets * (1f64 - (1f64 - 1f64 / buckets).powf(additions));
+            let cost = additions + 2f64 * used_buckets + c as f64 + n / 16f64 + best[end as usize];
+            if cost < best[start as usize] {
+                best[start as usize] = cost;
+                choice[start as usize] = c;
+            }
+        }
+    }
+
+    let mut schedule = vec![];
+    let mut skip = 0;
+    while skip < num_bits {
+        let c = choice[skip as usize];
+        schedule.push((skip, c));
+        skip += c;
+    }
+
+    schedule
+}
+
+/// Same as `dense_multiexp`, but with a window width picked separately for
+/// every region from the bit statistics of the exponents, see `adaptive_schedule`.
+pub fn dense_multiexp_adaptive<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+
+    let schedule = adaptive_schedule(exponents, <G::Engine as ScalarEngine>::Fr::NUM_BITS);
+
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), schedule.len() as u32, |region, range| {
+        let (skip, c) = schedule[region as usize];
+
+        dense_multiexp_chunk::<_, Limbs64>(&bases[range.clone()], &exponents[range], skip, c, skip == 0)
+    }, |region, partials| {
+        // the window above this one starts `c` bits higher
+        add_region(&mut acc, schedule[region as usize].1, &partials);
+
+        Ok(())
+    })?;
+
+    Ok(acc)
+}
+
+/// Same as `dense_multiexp`, but reads the scalar windows through the limb
+/// view `L`, e.g. `Limbs32` on targets where 64-bit words are slow.
+pub fn dense_multiexp_with_limbs<G: CurveAffine, L: LimbView>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    dense_multiexp_inner::<_, L>(pool, bases, exponents, 0, c, true)
+}
+
+/// Extraction of the `c`-bit window starting at bit `skip` of a scalar
+/// representation, so that the bucket logic does not depend on limb width.
+/// Bits past the end of the representation read as zero.
+pub trait LimbView {
+    fn window<R: PrimeFieldRepr>(repr: &R, skip: u32, c: u32) -> u64;
+}
+
+/// Reads windows straight out of the native 64-bit limbs; `c` must be below 64.
+pub struct Limbs64;
+
+impl LimbView for Limbs64 {
+    #[inline(always)]
+    fn window<R: PrimeFieldRepr>(repr: &R, skip: u32, c: u32) -> u64 {
+        let limbs = repr.as_ref();
+        let limb = (skip / 64) as usize;
+        let offset = skip % 64;
+        if limb >= limbs.len() {
+            return 0;
+        }
+
+        let mut window = limbs[limb] >> offset;
+        if offset + c > 64 && limb + 1 < limbs.len() {
+            window |= limbs[limb + 1] << (64 - offset);
+        }
+
+        window & ((1u64 << c) - 1)
+    }
+}
+
+/// Reads windows from the scalar viewed as little endian 32-bit words, using
+/// only 32-bit shifts; `c` must be at most 32.
+pub struct Limbs32;
+
+impl Limbs32 {
+    #[inline(always)]
+    fn word(limbs: &[u64], idx: usize) -> u32 {
+        match limbs.get(idx / 2) {
+            Some(limb) => (limb >> (32 * (idx % 2))) as u32,
+            None => 0
+        }
+    }
+}
+
+impl LimbView for Limbs32 {
+    #[inline(always)]
+    fn window<R: PrimeFieldRepr>(repr: &R, skip: u32, c: u32) -> u64 {
+        let limbs = repr.as_ref();
+        let idx = (skip / 32) as usize;
+        let offset = skip % 32;
+
+        let mut window = Self::word(limbs, idx) >> offset;
+        if offset + c > 32 {
+            window |= Self::word(limbs, idx + 1) << (32 - offset);
+        }
+
+        if c == 32 {
+            u64::from(window)
+        } else {
+            u64::from(window & ((1u32 << c) - 1))
+        }
+    }
+}
+
+/// Same as `dense_multiexp`, but also reports the last level cache misses,
+/// retired instructions and cycles of the calling thread and of the scoped
+/// threads it spawns for the regions, over the whole computation.
+/// Counts are zero if the kernel does not give access to the counters.
+#[cfg(all(feature = "perf-counters", target_os = "linux"))]
+pub fn multiexp_with_perf_counters<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G as CurveAffine>::Projective, crate::perf::PerfStats), SynthesisError>
+{
+    let (result, stats) = crate::perf::with_perf_counters(|| dense_multiexp(pool, bases, exponents));
+
+    Ok((result?, stats))
+}
+
+/// Number of pieces the chunk of one thread is cut into in `dense_multiexp`,
+/// which threads take one at a time until all are gone.
+const STEALING_GRAINS_PER_CHUNK: usize = 16;
+
+fn dense_multiexp_inner<G: CurveAffine, L: LimbView>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    mut skip: u32,
+    c: u32,
+    handle_trivial: bool
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{   
+    // Perform this region of the multiexp. We use a different strategy - go over region in parallel,
+    // then over another region, etc. Rather than a fixed chunk each, every thread keeps
+    // taking the next `grain` pairs from a shared cursor into its own buckets until none
+    // are left, so threads that get cheap pairs (zero or one scalars) take more of them.
+    // Every thread writes its partial sum into its own slot, and the slots are added up
+    // once the scope is joined, so there is no lock. A thread that panics leaves
+    // `WorkerFailure` in its slot instead
+    let this = {
+        let span = region_span(&Span::current(), skip, c);
+        let _enter = span.enter();
+
+        // The highest region may be narrower, it only needs buckets for its own bits
+        let width = std::cmp::min(c, <G::Engine as ScalarEngine>::Fr::NUM_BITS - skip);
+        let chunk = pool.get_chunk_size(bases.len());
+        let grain = std::cmp::max(chunk / STEALING_GRAINS_PER_CHUNK, 1);
+        let cursor = AtomicUsize::new(0);
+        let mut partials = (0..bases.chunks(chunk).len()).map(|_| Err(SynthesisError::WorkerFailure)).collect::<Vec<_>>();
+
+        let work = || -> Result<_, SynthesisError> {
+            let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << width) - 1];
+            let mut acc = G::Projective::zero();
+            loop {
+                let start = cursor.fetch_add(grain, Ordering::Relaxed);
+                if start >= bases.len() {
+                    break;
+                }
+                let end = std::cmp::min(start + grain, bases.len());
+                acc.add_assign(&dense_multiexp_fill_buckets::<_, L>(
+                    &bases[start..end], &exponents[start..end], skip, width, handle_trivial, &mut buckets));
+            }
+            acc.add_assign(&serial_sum_buckets(&buckets));
+
+            Ok(acc)
+        };
+        let work = &work;
+
+        if let [partial] = &mut partials[..] {
+            // A single thread would take all the work, so no need to spawn it
+            *partial = catch_worker_panic(work);
+        } else {
+            // With `linux-affinity` every thread stays on its own CPU
+            #[cfg(all(feature = "linux-affinity", feature = "multicore", target_os = "linux"))]
+            pool.scope_pinned(bases.len(), |scope, _| {
+                for partial in partials.iter_mut() {
+                    scope.spawn(move |_| *partial = catch_worker_panic(work));
+                }
+            });
+            #[cfg(not(all(feature = "linux-affinity", feature = "multicore", target_os = "linux")))]
+            pool.scope(bases.len(), |scope, _| {
+                for partial in partials.iter_mut() {
+                    scope.spawn(move |_| *partial = catch_worker_panic(work));
+                }
+            });
+        }
+
+        let mut this_region = <G as CurveAffine>::Projective::zero();
+        for partial in partials.into_iter() {
+            this_region.add_assign(&partial?);
+        }
+
+        this_region
+    };
+
+    skip += c;
+
+    if skip >= <G::Engine as ScalarEngine>::Fr::NUM_BITS {
+        // There isn't another region, and this will be the highest region
+        return Ok(this);
+    } else {
+        // next region is actually higher than this one, so double it enough times
+        let mut next_region = dense_multiexp_inner::<_, L>(
+            pool, bases, exponents, skip, c, false)?;
+        for _ in 0..c {
+            next_region.double();
+        }
+
+        next_region.add_assign(&this);
+
+        return Ok(next_region);
+    }
+}
+
+/// Drive the regions of a dense multiexp over `len` pairs, from the highest of
+/// `num_regions` down. Every region is split into one chunk of pairs per
+/// thread, `chunk(region, range)` runs on each of them, and `region_done`
+/// gets their results in the order of the pairs once all of them are back.
+/// A chunk that panics fails the whole run with `SynthesisError::WorkerFailure`.
+fn for_each_region<T, K, F>(
+    pool: &Worker,
+    len: usize,
+    num_regions: u32,
+    chunk: K,
+    mut region_done: F
+) -> Result<(), SynthesisError>
+    where T: Send,
+          K: Fn(u32, Range<usize>) -> T + Sync,
+          F: FnMut(u32, Vec<T>) -> Result<(), SynthesisError>
+{
+    let num_chunks = len.div_ceil(pool.get_chunk_size(len));
+    let chunk = &chunk;
+
+    for region in (0..num_regions).rev() {
+        let mut results = (0..num_chunks).map(|_| Err(SynthesisError::WorkerFailure)).collect::<Vec<_>>();
+
+        pool.scope(len, |scope, chunk_size| {
+            for (i, result) in results.iter_mut().enumerate() {
+                let range = (i * chunk_size)..std::cmp::min((i + 1) * chunk_size, len);
+                scope.spawn(move |_| *result = catch_worker_panic(|| Ok(chunk(region, range))));
+            }
+        });
+
+        region_done(region, results.into_iter().collect::<Result<Vec<_>, _>>()?)?;
+    }
+
+    Ok(())
+}
+
+/// Shift `acc` up by a window of `c` bits and add the chunk results of the
+/// region right below it.
+fn add_region<P: CurveProjective>(acc: &mut P, c: u32, partials: &[P]) {
+    for _ in 0..c {
+        acc.double();
+    }
+    for partial in partials.iter() {
+        acc.add_assign(partial);
+    }
+}
+
+/// Address of the first base of the slice that `dense_multiexp_fill_buckets`
+/// panics on, for testing how worker failures are reported.
+#[cfg(test)]
+static INJECTED_CHUNK_PANIC: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Accumulates the region starting at bit `skip` over one chunk of bases, with the
+/// trivial `exp == 1` terms added directly if `handle_trivial` is set.
+fn dense_multiexp_chunk<G: CurveAffine, L: LimbView>(
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    skip: u32,
+    c: u32,
+    handle_trivial: bool
+) -> <G as CurveAffine>::Projective
+{
+    if bases.is_empty() {
+        return G::Projective::zero();
+    }
+
+    let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+
+    dense_multiexp_chunk_with_buckets::<_, L>(bases, exponents, skip, c, handle_trivial, &mut buckets)
+}
+
+/// Same as `dense_multiexp_chunk`, with the `2^c - 1` buckets, all set to the
+/// identity, supplied by the caller.
+fn dense_multiexp_chunk_with_buckets<G: CurveAffine, L: LimbView>(
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    skip: u32,
+    c: u32,
+    handle_trivial: bool,
+    buckets: &mut [<G as CurveAffine>::Projective]
+) -> <G as CurveAffine>::Projective
+{
+    debug_assert_eq!(buckets.len(), (1 << c) - 1);
+
+    if bases.is_empty() {
+        return G::Projective::zero();
+    }
+
+    let mut acc = dense_multiexp_fill_buckets::<_, L>(bases, exponents, skip, c, handle_trivial, buckets);
+    // buckets are filled with the corresponding accumulated value, now sum
+    acc.add_assign(&serial_sum_buckets(buckets));
+
+    acc
+}
+
+/// Sort one slice of bases into `buckets` for the region starting at bit
+/// `skip`, without summing them up, so that several slices can share the
+/// buckets. Returns the sum of the trivial `exp == 1` terms if
+/// `handle_trivial` is set, and the identity otherwise. With `c == 1` the
+/// single bucket is skipped and its bases go into the returned sum as well.
+fn dense_multiexp_fill_buckets<G: CurveAffine, L: LimbView>(
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    skip: u32,
+    c: u32,
+    handle_trivial: bool,
+    buckets: &mut [<G as CurveAffine>::Projective]
+) -> <G as CurveAffine>::Projective
+{
+    #[cfg(test)]
+    {
+        if INJECTED_CHUNK_PANIC.load(Ordering::SeqCst) == bases.as_ptr() as usize {
+            panic!("injected chunk failure");
+        }
+    }
+
+    let mut acc = G::Projective::zero();
+
+    let zero = <G::Engine as ScalarEngine>::Fr::zero().into_repr();
+    let one = <G::Engine as ScalarEngine>::Fr::one().into_repr();
+
+    for (base, &exp) in bases.iter().zip(exponents.iter()) {
+        if exp != zero {
+            if exp == one {
+                if handle_trivial {
+                    acc.add_assign_mixed(base);
+                }
+            } else {
+                let exp = L::window(&exp, skip, c);
+                if exp != 0 && c == 1 {
+                    acc.add_assign_mixed(base);
+                } else if exp != 0 {
+                    buckets[(exp - 1) as usize].add_assign_mixed(base);
+                }
+            }
+        }
+    }
+
+    acc
+}
+
+/// Pull based multi-exponentiation for cooperative scheduling on a single thread.
+/// Every call to `next()` processes one window of `c` bits of all the exponents,
+/// so the caller can interleave other work between the steps. Once the iterator
+/// is exhausted `finish()` returns the same value as `dense_multiexp`.
+pub struct MultiexpIterator<'a, G: CurveAffine> {
+    bases: &'a [G],
+    exponents: &'a [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    c: u32,
+    remaining_regions: u32,
+    acc: <G as CurveAffine>::Projective
+}
+
+impl<'a, G: CurveAffine> MultiexpIterator<'a, G> {
+    pub fn new(
+        bases: &'a [G],
+        exponents: &'a [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+    ) -> Result<Self, SynthesisError>
+    {
+        check_lengths(bases.len(), exponents.len())?;
+        let c = window_size(exponents.len());
+
+        Ok(MultiexpIterator {
+            bases,
+            exponents,
+            c,
+            remaining_regions: <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c),
+            acc: G::Projective::zero()
+        })
+    }
+
+    /// Number of steps left before the result is ready.
+    pub fn remaining_steps(&self) -> usize {
+        self.remaining_regions as usize
+    }
+
+    /// Return the result, running any steps that were not pulled yet.
+    pub fn finish(mut self) -> <G as CurveAffine>::Projective {
+        while self.next().is_some() {}
+
+        self.acc
+    }
+}
+
+impl<'a, G: CurveAffine> Iterator for MultiexpIterator<'a, G> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<()> {
+        if self.remaining_regions == 0 {
+            return None;
+        }
+        self.remaining_regions -= 1;
+
+        // windows are processed from the most significant one, so the
+        // accumulator only ever has to be shifted up by one window
+        let region = self.remaining_regions;
+        for _ in 0..self.c {
+            self.acc.double();
+        }
+        let partial = dense_multiexp_chunk::<_, Limbs64>(self.bases, self.exponents, region * self.c, self.c, region == 0);
+        self.acc.add_assign(&partial);
+
+        Some(())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining_steps(), Some(self.remaining_steps()))
+    }
+}
+
+/// Perform multi-exponentiation over `bases[..split_at]` and `bases[split_at..]` at
+/// once, returning both partial results. Every base is read a single time per region,
+/// which matches the halving rounds of inner product arguments. Fails with
+/// `SynthesisError::SplitOutOfBounds` if `split_at > bases.len()`.
+pub fn multiexp_split<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    split_at: usize
+) -> Result<(<G as CurveAffine>::Projective, <G as CurveAffine>::Projective), SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    if split_at > bases.len() {
+        return Err(SynthesisError::SplitOutOfBounds {
+            split_at,
+            len: bases.len()
+        });
+    }
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    let mut left = G::Projective::zero();
+    let mut right = G::Projective::zero();
+
+    for_each_region(pool, bases.len(), <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c), |region, range| {
+        let skip = region * c;
+        // only the chunk containing `split_at` has bases on both sides
+        let mid = split_at.clamp(range.start, range.end);
+
+        (
+            dense_multiexp_chunk::<_, Limbs64>(&bases[range.start..mid], &exponents[range.start..mid], skip, c, skip == 0),
+            dense_multiexp_chunk::<_, Limbs64>(&bases[mid..range.end], &exponents[mid..range.end], skip, c, skip == 0)
+        )
+    }, |_, partials| {
+        let (l, r): (Vec<_>, Vec<_>) = partials.into_iter().unzip();
+        add_region(&mut left, c, &l);
+        add_region(&mut right, c, &r);
+
+        Ok(())
+    })?;
+
+    Ok((left, right))
+}
+
+/// Perform multi-exponentiation of the same exponents against two base vectors
+/// of a group, e.g. a commitment and its blinding part. Every window digit is
+/// extracted once and used for both bucket sets.
+pub fn multiexp_dual_bases<G: CurveAffine>(
+    pool: &Worker,
+    bases_a: & [G],
+    bases_b: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G as CurveAffine>::Projective, <G as CurveAffine>::Projective), SynthesisError>
+{
+    check_lengths(bases_a.len(), exponents.len())?;
+    check_lengths(bases_b.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    let mut acc_a = G::Projective::zero();
+    let mut acc_b = G::Projective::zero();
+
+    for_each_region(pool, exponents.len(), <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c), |region, range| {
+        let skip = region * c;
+        let mut buckets_a = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+        let mut buckets_b = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+
+        for ((a, b), exp) in bases_a[range.clone()].iter().zip(bases_b[range.clone()].iter()).zip(exponents[range].iter()) {
+            let digit = Limbs64::window(exp, skip, c);
+            if digit != 0 {
+                buckets_a[(digit - 1) as usize].add_assign_mixed(a);
+                buckets_b[(digit - 1) as usize].add_assign_mixed(b);
+            }
+        }
+
+        (serial_sum_buckets(&buckets_a), serial_sum_buckets(&buckets_b))
+    }, |_, partials| {
+        let (a, b): (Vec<_>, Vec<_>) = partials.into_iter().unzip();
+        add_region(&mut acc_a, c, &a);
+        add_region(&mut acc_b, c, &b);
+
+        Ok(())
+    })?;
+
+    Ok((acc_a, acc_b))
+}
+
+/// Perform a G1 and a G2 multi-exponentiation with the same exponents, interleaving
+/// the bucket additions of both groups in a single loop. Base and extension field
+/// arithmetic load different execution units, so a software-pipelined loop can keep
+/// more of them busy than two separate multiexps would.
+#[cfg(feature = "experimental")]
+pub fn multiexp_interleaved_g1_g2<G1, G2>(
+    pool: &Worker,
+    g1_bases: & [G1],
+    g2_bases: & [G2],
+    exponents: & [<<G1::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G1 as CurveAffine>::Projective, <G2 as CurveAffine>::Projective), SynthesisError>
+    where G1: CurveAffine,
+          G2: CurveAffine<Engine = G1::Engine>
+{
+    check_lengths(g1_bases.len(), exponents.len())?;
+    check_lengths(g2_bases.len(), exponents.len())?;
+    // the G2 buckets are the larger ones, so they are what has to fit in cache
+    let c = cache_window_size::<G2>(pool, exponents.len());
+
+    let mut g1_acc = G1::Projective::zero();
+    let mut g2_acc = G2::Projective::zero();
+
+    for_each_region(pool, exponents.len(), <G1::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c), |region, range| {
+        let skip = region * c;
+        let mut g1_buckets = vec![<G1 as CurveAffine>::Projective::zero(); (1 << c) - 1];
+        let mut g2_buckets = vec![<G2 as CurveAffine>::Projective::zero(); (1 << c) - 1];
+
+        for ((g1, g2), &exp) in g1_bases[range.clone()].iter().zip(g2_bases[range.clone()].iter()).zip(exponents[range].iter()) {
+            let exp = Limbs64::window(&exp, skip, c);
+            if exp != 0 {
+                g1_buckets[(exp - 1) as usize].add_assign_mixed(g1);
+                g2_buckets[(exp - 1) as usize].add_assign_mixed(g2);
+            }
+        }
+
+        (serial_sum_buckets(&g1_buckets), serial_sum_buckets(&g2_buckets))
+    }, |_, partials| {
+        let (g1, g2): (Vec<_>, Vec<_>) = partials.into_iter().unzip();
+        add_region(&mut g1_acc, c, &g1);
+        add_region(&mut g2_acc, c, &g2);
+
+        Ok(())
+    })?;
+
+    Ok((g1_acc, g2_acc))
+}
+
+/// Perform multi-exponentiation with small integer scalars, e.g. the multiplicities
+/// of a lookup argument. Only the bits up to the highest set bit of the largest
+/// scalar are processed, and digits are read straight from the `u64` instead of
+/// shifting full field representations.
+pub fn dense_multiexp_u64<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    scalars: & [u64]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), scalars.len())?;
+
+    let max = scalars.iter().cloned().max().unwrap_or(0);
+    let bits = 64 - max.leading_zeros();
+    let mut acc = G::Projective::zero();
+    if bits == 0 {
+        return Ok(acc);
+    }
+    let c = std::cmp::min(cache_window_size::<G>(pool, scalars.len()), bits);
+    let mask = (1u64 << c) - 1;
+
+    for_each_region(pool, bases.len(), bits.div_ceil(c), |region, range| {
+        let skip = region * c;
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); mask as usize];
+        for (base, &scalar) in bases[range.clone()].iter().zip(scalars[range].iter()) {
+            let digit = (scalar >> skip) & mask;
+            if digit != 0 {
+                buckets[(digit - 1) as usize].add_assign_mixed(base);
+            }
+        }
+
+        serial_sum_buckets(&buckets)
+    }, |_, partials| {
+        add_region(&mut acc, c, &partials);
+
+        Ok(())
+    })?;
+
+    Ok(acc)
+}
+
+/// Fold a bucket index into a per-region trace. The avalanche step is the
+/// splitmix64 finalizer, summing the mixed values keeps the trace independent
+/// of the order in which bases are visited.
+#[cfg(any(test, feature = "fuzz"))]
+fn trace_bucket(region: u32, bucket: u64) -> u64 {
+    let mut z = (u64::from(region) << 48 ^ bucket).wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Perform multi-exponentiation as `dense_multiexp` does, and also return a
+/// cheap trace of the bucket indices that every region used. Traces depend only
+/// on the exponents and the window, so two implementations that disagree on the
+/// final point but agree on the trace diverge in their additions, not in their
+/// digit decomposition. Meant for differential fuzzing only.
+#[cfg(any(test, feature = "fuzz"))]
+pub fn multiexp_with_trace<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G as CurveAffine>::Projective, u64), SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    let mut acc = G::Projective::zero();
+    let mut trace = 0u64;
+    for_each_region(pool, bases.len(), <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c), |region, range| {
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+        let mut trace = 0u64;
+
+        for (base, &exp) in bases[range.clone()].iter().zip(exponents[range].iter()) {
+            let exp = Limbs64::window(&exp, region * c, c);
+            if exp != 0 {
+                buckets[(exp - 1) as usize].add_assign_mixed(base);
+                trace = trace.wrapping_add(trace_bucket(region, exp));
+            }
+        }
+
+        (serial_sum_buckets(&buckets), trace)
+    }, |_, partials| {
+        // the trace of a region is a sum, so its chunks just add up
+        let (partials, traces): (Vec<_>, Vec<_>) = partials.into_iter().unzip();
+        add_region(&mut acc, c, &partials);
+        trace = trace.rotate_left(7) ^ traces.into_iter().fold(0u64, u64::wrapping_add);
+
+        Ok(())
+    })?;
+
+    Ok((acc, trace))
+}
+
+/// Convert `points` into affine form, normalizing them in place along the way.
+/// Every worker chunk is batch normalized with a single field inversion;
+/// points at infinity are left out of it and come out as the affine identity.
+pub fn batch_normalize<G: CurveProjective>(pool: &Worker, points: &mut [G]) -> Vec<G::Affine> {
+    let mut affine = vec![G::Affine::zero(); points.len()];
+
+    pool.scope(points.len(), |scope, chunk| {
+        for (points, affine) in points.chunks_mut(chunk)
+                    .zip(affine.chunks_mut(chunk)) {
+            scope.spawn(move |_| {
+                G::batch_normalization(points);
+                // every point is normalized now, so this is just a conversion
+                for (p, a) in points.iter().zip(affine.iter_mut()) {
+                    *a = p.into_affine();
+                }
+            });
+        }
+    });
+
+    affine
+}
+
+/// Convert multiexp results of both source groups into affine form for pairing.
+/// Each group is batch normalized with a single field inversion, and the two
+/// groups are processed concurrently.
+pub fn normalize_batch_for_pairing<E: Engine>(
+    pool: &Worker,
+    g1_points: &[E::G1],
+    g2_points: &[E::G2]
+) -> (Vec<E::G1Affine>, Vec<E::G2Affine>)
+{
+    let mut g1 = g1_points.to_vec();
+    let mut g2 = g2_points.to_vec();
+
+    pool.scope(2, |scope, _| {
+        let g1 = &mut g1;
+        let g2 = &mut g2;
+        scope.spawn(move |_| E::G1::batch_normalization(g1));
+        scope.spawn(move |_| E::G2::batch_normalization(g2));
+    });
+
+    // every point is normalized now, so this is just a conversion
+    (
+        g1.into_iter().map(|p| p.into_affine()).collect(),
+        g2.into_iter().map(|p| p.into_affine()).collect()
+    )
+}
+
+/// Bounded cache of dense multiexp results for workloads where the same
+/// `(bases, exponents)` pairs are queried over and over. Queries are keyed by
+/// two independently and randomly keyed SipHash digests of the exponent limbs
+/// and the uncompressed bases, and the least recently used entry is dropped
+/// once `capacity` results are stored.
+pub struct MemoizedMultiexp<G: CurveAffine> {
+    capacity: usize,
+    hashers: (RandomState, RandomState),
+    entries: HashMap<(u64, u64), (<G as CurveAffine>::Projective, u64)>,
+    last_use: u64
+}
+
+impl<G: CurveAffine> MemoizedMultiexp<G> {
+    pub fn new(capacity: usize) -> Self {
+        MemoizedMultiexp {
+            capacity,
+            hashers: (RandomState::new(), RandomState::new()),
+            entries: HashMap::with_capacity(capacity),
+            last_use: 0
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn key(
+        &self,
+        bases: & [G],
+        exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+    ) -> (u64, u64)
+    {
+        let mut first = self.hashers.0.build_hasher();
+        let mut second = self.hashers.1.build_hasher();
+
+        bases.len().hash(&mut first);
+        bases.len().hash(&mut second);
+        for (base, exp) in bases.iter().zip(exponents.iter()) {
+            let encoding = base.into_uncompressed();
+            first.write(encoding.as_ref());
+            second.write(encoding.as_ref());
+            exp.as_ref().hash(&mut first);
+            exp.as_ref().hash(&mut second);
+        }
+
+        (first.finish(), second.finish())
+    }
+
+    /// Return the cached result for these pairs, computing and caching it with
+    /// `dense_multiexp` on a miss.
+    pub fn multiexp(
+        &mut self,
+        pool: &Worker,
+        bases: & [G],
+        exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+    ) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    {
+        check_lengths(bases.len(), exponents.len())?;
+
+        let key = self.key(bases, exponents);
+        self.last_use += 1;
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.1 = self.last_use;
+            return Ok(entry.0);
+        }
+
+        let result = dense_multiexp(pool, bases, exponents)?;
+
+        if self.capacity == 0 {
+            return Ok(result);
+        }
+        if self.entries.len() == self.capacity {
+            // capacities are small, so a linear scan is cheaper than keeping an order
+            let oldest = *self.entries.iter().min_by_key(|(_, entry)| entry.1).expect("cache is not empty").0;
+            self.entries.remove(&oldest);
+        }
+        self.entries.insert(key, (result, self.last_use));
+
+        Ok(result)
+    }
+}
+
+/// Multi-exponentiation against a base set that never changes, such as the
+/// CRS. Every base is stored shifted by each window, `2^(j * w) * P` for
+/// `j` in `0..ceil(NUM_BITS / w)`, so a multiexp is a single bucketing pass
+/// over all the shifted bases, with no per-region bucket sums or doublings.
+pub struct FixedBaseMultiexp<G: CurveAffine> {
+    table: Vec<G>,
+    len: usize,
+    window: u32,
+    regions: u32
+}
+
+impl<G: CurveAffine> FixedBaseMultiexp<G> {
+    /// Precompute the table for `bases` with windows of `window_bits` bits.
+    /// Wider windows make the table smaller and the multiexp cheaper, up to
+    /// the point where summing the `2^w - 1` buckets starts to dominate.
+    pub fn new(pool: &Worker, bases: & [G], window_bits: u32) -> Result<Self, SynthesisError> {
+        if window_bits == 0 || window_bits > MAX_WINDOW_SIZE {
+            return Err(SynthesisError::InvalidWindow(window_bits));
+        }
+        let regions = <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(window_bits);
+
+        let mut shifted = vec![G::Projective::zero(); bases.len() * regions as usize];
+        pool.scope(bases.len(), |scope, chunk| {
+            for (base, shifted) in bases.chunks(chunk)
+                        .zip(shifted.chunks_mut(chunk * regions as usize)) {
+                scope.spawn(move |_| {
+                    for (base, shifted) in base.iter().zip(shifted.chunks_mut(regions as usize)) {
+                        let mut p = base.into_projective();
+                        for entry in shifted.iter_mut() {
+                            *entry = p;
+                            for _ in 0..window_bits {
+                                p.double();
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        let table = batch_normalize(pool, &mut shifted);
+
+        Ok(FixedBaseMultiexp {
+            table,
+            len: bases.len(),
+            window: window_bits,
+            regions
+        })
+    }
+
+    /// Memory taken by the precomputed table.
+    pub fn table_bytes(&self) -> usize {
+        self.table.len() * std::mem::size_of::<G>()
+    }
+
+    /// Perform multi-exponentiation of the fixed bases by `exponents`.
+    pub fn multiexp(
+        &self,
+        pool: &Worker,
+        exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+    ) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+    {
+        check_lengths(self.len, exponents.len())?;
+        let (w, regions) = (self.window, self.regions as usize);
+
+        let chunk = pool.get_chunk_size(exponents.len());
+        let mut partials = vec![<G as CurveAffine>::Projective::zero(); exponents.chunks(chunk).len()];
+
+        pool.scope(exponents.len(), |scope, chunk| {
+            for ((exp, table), partial) in exponents.chunks(chunk)
+                        .zip(self.table.chunks(chunk * regions))
+                        .zip(partials.iter_mut()) {
+                scope.spawn(move |_| {
+                    let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << w) - 1];
+                    for (exp, shifted) in exp.iter().zip(table.chunks(regions)) {
+                        for (region, base) in shifted.iter().enumerate() {
+                            let digit = Limbs64::window(exp, region as u32 * w, w);
+                            if digit != 0 {
+                                buckets[(digit - 1) as usize].add_assign_mixed(base);
+                            }
+                        }
+                    }
+                    *partial = serial_sum_buckets(&buckets);
+                });
+            }
+        });
+
+        let mut acc = G::Projective::zero();
+        for partial in partials.iter() {
+            acc.add_assign(partial);
+        }
+
+        Ok(acc)
+    }
+}
+
+/// Widest window a `DigitMatrix` holds, its digits are `u16`.
+pub const MAX_DIGIT_WINDOW_SIZE: u32 = 16;
+
+/// Widest scalars a serialized `DigitMatrix` may cover, which bounds its
+/// number of regions to `MAX_DIGIT_MATRIX_BITS.div_ceil(c)`.
+pub const MAX_DIGIT_MATRIX_BITS: u32 = 256;
+
+/// Window digits of a set of scalars, computed ahead of time (possibly by an
+/// external tool) and consumed by `multiexp_from_digit_matrix`. Region `r`
+/// holds bits `[r * c, (r + 1) * c)` of every scalar, so a scalar equals
+/// `sum_r digit(r) * 2^(r * c)`. Digits are stored region-major, which keeps
+/// all the digits one bucketing pass reads contiguous.
+///
+/// Serialized layout, all integers little endian:
+///
+/// ```text
+/// offset  size                     field
+/// 0       4                        magic, the ASCII bytes `BDMX`
+/// 4       4                        c, window width in bits, 1..=16
+/// 8       4                        R, number of regions, 1..=ceil(256 / c)
+/// 12      8                        N, number of scalars
+/// 20      2 * R * N                digits as u16, digit of scalar `i` in region
+///                                  `r` at index `r * N + i`, each below 2^c
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigitMatrix {
+    c: u32,
+    num_regions: usize,
+    num_scalars: usize,
+    digits: Vec<u16>
+}
+
+impl DigitMatrix {
+    pub const MAGIC: [u8; 4] = *b"BDMX";
+
+    /// Decompose `exponents` into `c`-bit windows covering `num_bits` bits.
+    /// Windows outside of `1..=MAX_DIGIT_WINDOW_SIZE` do not fit the `u16`
+    /// digits and are rejected with `SynthesisError::InvalidWindow`.
+    pub fn from_exponents<R: PrimeFieldRepr>(exponents: &[R], num_bits: u32, c: u32) -> Result<Self, SynthesisError> {
+        if !(1..=MAX_DIGIT_WINDOW_SIZE).contains(&c) {
+            return Err(SynthesisError::InvalidWindow(c));
+        }
+        let num_regions = num_bits.div_ceil(c) as usize;
+
+        let mut digits = Vec::with_capacity(num_regions * exponents.len());
+        for region in 0..num_regions {
+            digits.extend(exponents.iter().map(|exp| Limbs64::window(exp, region as u32 * c, c) as u16));
+        }
+
+        Ok(DigitMatrix {
+            c,
+            num_regions,
+            num_scalars: exponents.len(),
+            digits
+        })
+    }
+
+    /// Same as `from_exponents`, with the digits extracted in parallel.
+    pub fn from_exponents_par<R: PrimeFieldRepr>(pool: &Worker, exponents: &[R], num_bits: u32, c: u32) -> Result<Self, SynthesisError> {
+        if !(1..=MAX_DIGIT_WINDOW_SIZE).contains(&c) {
+            return Err(SynthesisError::InvalidWindow(c));
+        }
+        let num_regions = num_bits.div_ceil(c) as usize;
+        let num_scalars = exponents.len();
+
+        let mut digits = vec![0u16; num_regions * num_scalars];
+        pool.scope(digits.len(), |scope, chunk| {
+            for (i, digits) in digits.chunks_mut(chunk).enumerate() {
+                scope.spawn(move |_| {
+                    for (j, digit) in digits.iter_mut().enumerate() {
+                        let idx = i * chunk + j;
+                        let skip = (idx / num_scalars) as u32 * c;
+                        *digit = Limbs64::window(&exponents[idx % num_scalars], skip, c) as u16;
+                    }
+                });
+            }
+        });
+
+        Ok(DigitMatrix {
+            c,
+            num_regions,
+            num_scalars,
+            digits
+        })
+    }
+
+    pub fn c(&self) -> u32 {
+        self.c
+    }
+
+    pub fn num_regions(&self) -> usize {
+        self.num_regions
+    }
+
+    pub fn num_scalars(&self) -> usize {
+        self.num_scalars
+    }
+
+    /// Digits of all the scalars in region `region`.
+    pub fn region(&self, region: usize) -> &[u16] {
+        &self.digits[region * self.num_scalars..(region + 1) * self.num_scalars]
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&Self::MAGIC)?;
+        writer.write_u32::<LittleEndian>(self.c)?;
+        writer.write_u32::<LittleEndian>(self.num_regions as u32)?;
+        writer.write_u64::<LittleEndian>(self.num_scalars as u64)?;
+        for &digit in self.digits.iter() {
+            writer.write_u16::<LittleEndian>(digit)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != Self::MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a digit matrix"));
+        }
+
+        let c = reader.read_u32::<LittleEndian>()?;
+        if !(1..=MAX_DIGIT_WINDOW_SIZE).contains(&c) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "window width must be between 1 and 16"));
+        }
+        let num_regions = reader.read_u32::<LittleEndian>()? as usize;
+        if num_regions == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "digit matrix has no regions"));
+        }
+        if num_regions > MAX_DIGIT_MATRIX_BITS.div_ceil(c) as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "digit matrix has more regions than a 256-bit scalar"));
+        }
+        let num_scalars = reader.read_u64::<LittleEndian>()? as usize;
+
+        let len = num_regions.checked_mul(num_scalars)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "digit matrix is too large"))?;
+        // don't trust the header with the allocation size
+        let mut digits = Vec::with_capacity(std::cmp::min(len, 1 << 20));
+        for _ in 0..len {
+            let digit = reader.read_u16::<LittleEndian>()?;
+            if u32::from(digit) >= 1 << c {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "digit does not fit into the window"));
+            }
+            digits.push(digit);
+        }
+
+        Ok(DigitMatrix {
+            c,
+            num_regions,
+            num_scalars,
+            digits
+        })
+    }
+}
+
+/// Intermediate values of the bucket summation of a multiexp, as returned by
+/// `multiexp_with_opening_data`. For every region, `running_sums(region)[i]`
+/// is the sum of the buckets of digits `i + 1` and above, i.e. of the bases
+/// whose digit in that region is above `i`. The region's share of the result
+/// is the sum of its running sums.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpeningData<G: CurveProjective> {
+    c: u32,
+    running_sums: Vec<Vec<G>>
+}
+
+impl<G: CurveProjective> OpeningData<G> {
+    pub fn c(&self) -> u32 {
+        self.c
+    }
+
+    pub fn num_regions(&self) -> usize {
+        self.running_sums.len()
+    }
+
+    /// The `2^c - 1` running sums of `region`, lowest digit first, or `None`
+    /// if there is no such region.
+    pub fn running_sums(&self, region: usize) -> Option<&[G]> {
+        self.running_sums.get(region).map(|sums| &sums[..])
+    }
+
+    /// Sum of the bases whose digit in `region` is `digit`, recovered as the
+    /// difference of two consecutive running sums. `None` unless `region` is
+    /// below `num_regions()` and `digit` is in `1..2^c`.
+    pub fn bucket(&self, region: usize, digit: usize) -> Option<G> {
+        let sums = self.running_sums.get(region)?;
+        let mut bucket = *sums.get(digit.checked_sub(1)?)?;
+        if let Some(next) = sums.get(digit) {
+            bucket.sub_assign(next);
+        }
+
+        Some(bucket)
+    }
+
+    /// Recombine the running sums into the multiexp result.
+    pub fn result(&self) -> G {
+        let mut acc = G::zero();
+        for sums in self.running_sums.iter().rev() {
+            for _ in 0..self.c {
+                acc.double();
+            }
+            for sum in sums.iter() {
+                acc.add_assign(sum);
+            }
+        }
+
+        acc
+    }
+}
+
+/// Same as `dense_multiexp`, but also returns the running sums that the
+/// summation by parts of every region goes through, for opening algorithms
+/// that reuse them. All scalars go through the buckets, including the
+/// trivial ones, so that the running sums account for every base. This keeps
+/// `2^c - 1` points per region alive until the end, and every chunk of a
+/// region hands its whole bucket array back to be merged.
+pub fn multiexp_with_opening_data<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<(<G as CurveAffine>::Projective, OpeningData<<G as CurveAffine>::Projective>), SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = cache_window_size::<G>(pool, exponents.len());
+
+    let num_regions = <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c);
+    let mut running_sums = vec![vec![]; num_regions as usize];
+
+    for_each_region(pool, bases.len(), num_regions, |region, range| {
+        let skip = region * c;
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+
+        for (base, exp) in bases[range.clone()].iter().zip(exponents[range].iter()) {
+            let digit = Limbs64::window(exp, skip, c);
+            if digit != 0 {
+                buckets[(digit - 1) as usize].add_assign_mixed(base);
+            }
+        }
+
+        buckets
+    }, |region, chunks| {
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+        for chunk in chunks.iter() {
+            for (bucket, partial) in buckets.iter_mut().zip(chunk.iter()) {
+                bucket.add_assign(partial);
+            }
+        }
+
+        // Summation by parts, keeping every running sum
+        let mut running_sum = <G as CurveAffine>::Projective::zero();
+        for bucket in buckets.iter_mut().rev() {
+            running_sum.add_assign(bucket);
+            *bucket = running_sum;
+        }
+        running_sums[region as usize] = buckets;
+
+        Ok(())
+    })?;
+
+    let data = OpeningData {
+        c,
+        running_sums
+    };
+
+    Ok((data.result(), data))
+}
+
+/// Perform multi-exponentiation with the scalars given by their window digits,
+/// region by region like `dense_multiexp`. A matrix with more regions than the
+/// scalar field needs for its window is rejected with
+/// `SynthesisError::InvalidWindow`.
+pub fn multiexp_from_digit_matrix<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    digits: &DigitMatrix
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), digits.num_scalars())?;
+    let c = digits.c();
+    if digits.num_regions() > <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(c) as usize {
+        return Err(SynthesisError::InvalidWindow(c));
+    }
+
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), digits.num_regions() as u32, |region, range| {
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+        for (base, &digit) in bases[range.clone()].iter().zip(digits.region(region as usize)[range].iter()) {
+            if digit != 0 {
+                buckets[(digit - 1) as usize].add_assign_mixed(base);
+            }
+        }
+
+        serial_sum_buckets(&buckets)
+    }, |_, partials| {
+        add_region(&mut acc, c, &partials);
+
+        Ok(())
+    })?;
+
+    Ok(acc)
+}
+
+/// Same as `dense_multiexp`, but all the window digits are extracted into a
+/// `DigitMatrix` up front, so that the bucketing passes index `u16` digits
+/// instead of shifting every scalar once per region. This costs two bytes per
+/// scalar and region, and the window is capped at 16 bits.
+pub fn dense_multiexp_with_digits<G: CurveAffine>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let c = std::cmp::min(cache_window_size::<G>(pool, exponents.len()), MAX_DIGIT_WINDOW_SIZE);
+    let digits = DigitMatrix::from_exponents_par(pool, exponents, <G::Engine as ScalarEngine>::Fr::NUM_BITS, c)?;
+
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, bases.len(), digits.num_regions() as u32, |region, range| {
+        let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+        for (base, &digit) in bases[range.clone()].iter().zip(digits.region(region as usize)[range].iter()) {
+            if digit != 0 {
+                buckets[(digit - 1) as usize].add_assign_mixed(base);
+            }
+        }
+
+        serial_sum_buckets(&buckets)
+    }, |_, partials| {
+        add_region(&mut acc, c, &partials);
+
+        Ok(())
+    })?;
+
+    Ok(acc)
+}
+
+/// Perform the multi-exponentiations of the same exponents and density by
+/// G1 and by G2 bases, e.g. the A and B queries of a Groth16 proof. The
+/// window digits of the exponents are extracted once into a `DigitMatrix`,
+/// in parallel before this returns, and shared by the region tasks of both
+/// groups instead of every task shifting the scalars again. The window is
+/// capped at 16 bits. The results are the same as those of two `multiexp`
+/// calls.
+pub fn multiexp_g1_g2<Q, D, G1, G2, S1, S2>(
+    pool: &Worker,
+    g1_bases: S1,
+    g2_bases: S2,
+    density_map: D,
+    exponents: Arc<Vec<<<G1::Engine as ScalarEngine>::Fr as PrimeField>::Repr>>
+) -> (ChunksJoiner< <G1 as CurveAffine>::Projective >, ChunksJoiner< <G2 as CurveAffine>::Projective >)
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G1: CurveAffine,
+          G2: CurveAffine<Engine = G1::Engine>,
+          S1: SourceBuilder<G1>,
+          S2: SourceBuilder<G2>
+{
+    let c = std::cmp::min(window_size(exponents.len()), MAX_DIGIT_WINDOW_SIZE);
+    let failed = |g1_err: SynthesisError, g2_err: SynthesisError| (
+        ChunksJoiner {
+            regions: vec![WorkerFuture::ready(Err(g1_err))],
+            acc: None,
+            c,
+            cancelled: Arc::new(AtomicBool::new(false))
+        },
+        ChunksJoiner {
+            regions: vec![WorkerFuture::ready(Err(g2_err))],
+            acc: None,
+            c,
+            cancelled: Arc::new(AtomicBool::new(false))
+        }
+    );
+
+    if let Some(query_size) = density_map.as_ref().get_query_size() {
+        if query_size != exponents.len() {
+            let err = || SynthesisError::LengthMismatch {
+                bases: query_size,
+                exponents: exponents.len()
+            };
+
+            return failed(err(), err());
+        }
+    }
+
+    let span = multiexp_span(exponents.len(), c);
+    let _enter = span.enter();
+
+    let num_bits = <G1::Engine as ScalarEngine>::Fr::NUM_BITS;
+    let digits = match DigitMatrix::from_exponents_par(pool, &exponents, num_bits, c) {
+        Ok(digits) => Arc::new(digits),
+        Err(_) => return failed(SynthesisError::InvalidWindow(c), SynthesisError::InvalidWindow(c))
+    };
+
+    (
+        multiexp_digit_regions(pool, g1_bases, density_map.clone(), digits.clone()),
+        multiexp_digit_regions(pool, g2_bases, density_map, digits)
+    )
+}
+
+/// Spawn a task per region of `digits`, bucketing the bases by their digit.
+fn multiexp_digit_regions<Q, D, G, S>(
+    pool: &Worker,
+    bases: S,
+    density_map: D,
+    digits: Arc<DigitMatrix>
+) -> ChunksJoiner< <G as CurveAffine>::Projective >
+    where for<'a> &'a Q: QueryDensity,
+          D: Send + Sync + 'static + Clone + AsRef<Q>,
+          G: CurveAffine,
+          S: SourceBuilder<G>
+{
+    let c = digits.c();
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    let regions = (0..digits.num_regions()).map(|region| {
+        let bases = bases.clone();
+        let density_map = density_map.clone();
+        let digits = digits.clone();
+        let cancelled = cancelled.clone();
+        let parent = Span::current();
+
+        pool.compute(move || catch_worker_panic(move || {
+            let span = region_span(&parent, region as u32 * c, c);
+            let _enter = span.enter();
+
+            let mut bases = bases.new();
+            let mut buckets = vec![<G as CurveAffine>::Projective::zero(); (1 << c) - 1];
+
+            for (&digit, density) in digits.region(region).iter().zip(density_map.as_ref().iter()) {
+                if cancelled.is_cancelled() {
+                    return Err(SynthesisError::Cancelled);
+                }
+                if density {
+                    if digit != 0 {
+                        bases.add_assign_mixed(&mut buckets[(digit - 1) as usize])?;
+                    } else {
+                        bases.skip(1)?;
+                    }
+                }
+            }
+
+            Ok(serial_sum_buckets(&buckets))
+        }))
+    }).collect();
+
+    ChunksJoiner {
+        regions,
+        acc: None,
+        c,
+        cancelled
+    }
+}
+
+/// Widest window `multiexp_const` accepts. Its buckets live on the stack of a
+/// worker thread, which is 2 MiB by default: `2^10 - 1` BLS12 G2 points take
+/// about 300 KiB, while 14 bits of G1 points would already overflow it.
+pub const MAX_CONST_WINDOW_SIZE: u32 = 10;
+
+/// Compile time check that `B` is the bucket count `2^C - 1` of a `C`-bit window,
+/// with `C` at most `MAX_CONST_WINDOW_SIZE`.
+struct ConstWindow<const C: u32, const B: usize>;
+
+impl<const C: u32, const B: usize> ConstWindow<C, B> {
+    const VALID: () = assert!(
+        C >= 1 && C <= MAX_CONST_WINDOW_SIZE && B == (1 << C) - 1,
+        "C must be in 1..=MAX_CONST_WINDOW_SIZE and B must be equal to 2^C - 1"
+    );
+}
+
+/// Perform multi-exponentiation of a fixed number `N` of pairs with a fixed window
+/// of `C` bits, at most `MAX_CONST_WINDOW_SIZE`. Regions are split over the worker
+/// like in `dense_multiexp`, with the `B = 2^C - 1` buckets of every chunk on the
+/// stack, so the only allocations are the chunk results of every region. Stable
+/// Rust cannot derive `B` from `C`, so it has to be spelled out; a wrong value or
+/// a wider window is rejected at compile time.
+pub fn multiexp_const<G: CurveAffine, const N: usize, const C: u32, const B: usize>(
+    pool: &Worker,
+    bases: & [G; N],
+    exponents: & [<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr; N]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    let () = ConstWindow::<C, B>::VALID;
+
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, N, <G::Engine as ScalarEngine>::Fr::NUM_BITS.div_ceil(C), |region, range| {
+        let skip = region * C;
+        let mut buckets = [<G as CurveAffine>::Projective::zero(); B];
+
+        for (base, &exp) in bases[range.clone()].iter().zip(exponents[range].iter()) {
+            let exp = Limbs64::window(&exp, skip, C);
+            if exp != 0 {
+                buckets[(exp - 1) as usize].add_assign_mixed(base);
+            }
+        }
+
+        serial_sum_buckets(&buckets)
+    }, |_, partials| {
+        add_region(&mut acc, C, &partials);
+
+        Ok(())
+    })?;
+
+    Ok(acc)
+}
+
+/// Bucket count from which `sum_buckets` splits the summation over the pool.
+/// Below it (`c < 16`) the serial scan is cheaper than spawning threads.
+pub const PARALLEL_SUMMATION_MIN_BUCKETS: usize = 1 << 16;
+
+/// Computes `1 * b_0 + 2 * b_1 + ... + n * b_{n-1}` for the buckets `b_i`,
+/// which is the accumulator of a region whose bucket `i` holds the bases
+/// with a window digit of `i + 1`. Large bucket sets are summed in parallel.
+pub fn sum_buckets<G: CurveProjective>(pool: &Worker, buckets: &[G]) -> G {
+    if buckets.len() < PARALLEL_SUMMATION_MIN_BUCKETS {
+        serial_sum_buckets(buckets)
+    } else {
+        parallel_sum_buckets(pool, buckets)
+    }
+}
+
+fn serial_sum_buckets<G: CurveProjective>(buckets: &[G]) -> G {
+    // A 1-bit window has a single bucket of weight one
+    if let [bucket] = buckets {
+        return *bucket;
+    }
+
+    // Summation by parts
+    // e.g. 3a + 2b + 1c = a +
+    //                    (a) + b +
+    //                    ((a) + b) + c
+    let mut acc = G::zero();
+    let mut running_sum = G::zero();
+    for exp in buckets.iter().rev() {
+        running_sum.add_assign(exp);
+        acc.add_assign(&running_sum);
+    }
+
+    acc
+}
+
+/// Two-level scan: every segment of buckets starting at `offset` is summed by parts
+/// with local weights `1, 2, ...`, and the missing `offset * segment_total` is added
+/// back with a short scalar multiplication once the segments are joined.
+fn parallel_sum_buckets<G: CurveProjective>(pool: &Worker, buckets: &[G]) -> G {
+    let chunk = pool.get_chunk_size(buckets.len());
+    let num_chunks = buckets.chunks(chunk).len();
+    let mut partials = vec![(G::zero(), G::zero()); num_chunks];
+
+    pool.scope(buckets.len(), |scope, chunk| {
+        for (segment, partial) in buckets.chunks(chunk).zip(partials.iter_mut()) {
+            scope.spawn(move |_| {
+                let mut total = G::zero();
+                for b in segment.iter() {
+                    total.add_assign(b);
+                }
+
+                *partial = (serial_sum_buckets(segment), total);
+            });
+        }
+    });
+
+    let mut acc = G::zero();
+    for (i, (weighted, mut total)) in partials.into_iter().enumerate() {
+        acc.add_assign(&weighted);
+        if i != 0 {
+            total.mul_assign((i * chunk) as u64);
+            acc.add_assign(&total);
+        }
+    }
+
+    acc
+}
+
+/// Engines whose `G1` group may have an efficiently computable endomorphism
+/// that a GLV-style multiexp can exploit.
+pub trait EndomorphismParams: Engine {
+    /// Whether `Self::G1Affine` implements `HasEndomorphism`, so callers can
+    /// dispatch between `dense_multiexp_glv` and the generic multiexp.
+    const HAS_ENDOMORPHISM: bool;
+}
+
+impl EndomorphismParams for crate::pairing::bn256::Bn256 {
+    const HAS_ENDOMORPHISM: bool = true;
+}
+
+impl EndomorphismParams for crate::pairing::bls12_381::Bls12 {
+    const HAS_ENDOMORPHISM: bool = true;
+}
+
+/// Lattice basis and rounding constants for splitting a scalar `k` into
+/// `k1 + k2 * λ` with `k1` and `k2` of about half the bit length.
+#[derive(Clone, Copy, Debug)]
+pub struct GlvBasis {
+    /// Short vectors `(a1, b1)` and `(a2, b2)` with `a + b * λ = 0 mod r`,
+    /// as two's complement 128-bit integers
+    pub a1: u128,
+    pub b1: u128,
+    pub a2: u128,
+    pub b2: u128,
+    /// `round(2^384 * n / r)` for the numerators `n` of the coordinates of
+    /// `(k, 0)` in the basis, as little endian limbs
+    pub g1: [u64; 5],
+    pub g2: [u64; 5]
+}
+
+/// Curves with an efficiently computable endomorphism `φ(x, y) = (β x, y)`
+/// equal to multiplication by `λ`, for `dense_multiexp_glv`. Implemented for
+/// the `G1` groups of the engines with `EndomorphismParams::HAS_ENDOMORPHISM`.
+pub trait HasEndomorphism: CurveAffine {
+    /// Cube root of unity `β` in the base field.
+    fn beta() -> Self::Base;
+
+    /// Cube root of unity `λ` in the scalar field matching `beta`.
+    fn lambda() -> Self::Scalar;
+
+    fn glv_basis() -> GlvBasis;
+
+    /// Apply the endomorphism, i.e. compute `λ * self` with one field
+    /// multiplication.
+    fn endomorphism(&self, beta: &Self::Base) -> Self {
+        if self.is_zero() {
+            return *self;
+        }
+        let (mut x, y) = self.into_xy_unchecked();
+        x.mul_assign(beta);
+
+        Self::from_xy_unchecked(x, y)
+    }
+}
+
+impl HasEndomorphism for crate::pairing::bn256::G1Affine {
+    fn beta() -> Self::Base {
+        crate::pairing::bn256::Fq::from_str("2203960485148121921418603742825762020974279258880205651966").expect("is a valid field element")
+    }
+
+    fn lambda() -> Self::Scalar {
+        crate::pairing::bn256::Fr::from_str("4407920970296243842393367215006156084916469457145843978461").expect("is a valid field element")
+    }
+
+    fn glv_basis() -> GlvBasis {
+        GlvBasis {
+            a1: 0x000000000000000089d3256894d213e3,
+            b1: 0x90b27db71147a6037dee441482b0eed8,
+            a2: 0x6f4d8248eeb859fd0be4e1541221250b,
+            b2: 0x000000000000000089d3256894d213e3,
+            g1: [0x8fa7d32d2fafba64, 0x6eb9c714773a6ef2, 0xd91d232ec7e0b3d7, 0x0000000000000002, 0x0000000000000000],
+            g2: [0x869375169b9bdffa, 0xa5e38cfb5eaa26d9, 0x7a7bd9d4391eb18d, 0x4ccef014a773d2cf, 0x0000000000000002]
+        }
+    }
+}
+
+impl HasEndomorphism for crate::pairing::bls12_381::G1Affine {
+    fn beta() -> Self::Base {
+        crate::pairing::bls12_381::Fq::from_str("4002409555221667392624310435006688643935503118305586438271171395842971157480381377015405980053539358417135540939436").expect("is a valid field element")
+    }
+
+    fn lambda() -> Self::Scalar {
+        crate::pairing::bls12_381::Fr::from_str("228988810152649578064853576960394133503").expect("is a valid field element")
+    }
+
+    fn glv_basis() -> GlvBasis {
+        GlvBasis {
+            a1: 0x00000000000000000000000000000001,
+            b1: 0xac45a4010001a4020000000100000000,
+            a2: 0xac45a4010001a40200000000ffffffff,
+            b2: 0xffffffffffffffffffffffffffffffff,
+            g1: [0x38b5dcb707e08ed3, 0x355094edfede377c, 0x0000000000000002, 0x0000000000000000, 0x0000000000000000],
+            g2: [0xda5e4f8d896c72da, 0x389f49a7268bf7a3, 0x63f6e522f6cfee30, 0x7c6becf1e01faadd, 0x0000000000000001]
+        }
+    }
+}
+
+/// `round(k * g / 2^384)`, or `None` if it does not fit 128 bits.
+fn mul_shift_384(k: &[u64], g: &[u64; 5]) -> Option<u128> {
+    let mut product = vec![0u64; k.len() + g.len() + 1];
+    for (i, k) in k.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, g) in g.iter().enumerate() {
+            let t = u128::from(*k) * u128::from(*g) + u128::from(product[i + j]) + carry;
+            product[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        product[i + g.len()] = carry as u64;
+    }
+
+    // Add 2^383 to round to the nearest
+    let mut carry = 1u64 << 63;
+    for limb in product[5..].iter_mut() {
+        let (sum, overflow) = limb.overflowing_add(carry);
+        *limb = sum;
+        carry = overflow as u64;
+    }
+
+    if product[8..].iter().any(|limb| *limb != 0) {
+        return None;
+    }
+
+    Some(u128::from(product[6]) | (u128::from(product[7]) << 64))
+}
+
+fn signed_scalar<F: PrimeField>(value: i128) -> F {
+    let mut repr = F::Repr::default();
+    let magnitude = value.unsigned_abs();
+    repr.as_mut()[0] = magnitude as u64;
+    repr.as_mut()[1] = (magnitude >> 64) as u64;
+    let mut scalar = F::from_repr(repr).expect("128-bit values are below the modulus");
+    if value < 0 {
+        scalar.negate();
+    }
+
+    scalar
+}
+
+/// Split `k` into signed `(k1, k2)` with `k = k1 + k2 * λ` and both below
+/// `2^127` in absolute value. Returns `None` for non-canonical scalars, and
+/// checks the split so that a rounding corner case is never silently wrong.
+fn glv_decompose<G: HasEndomorphism>(
+    k: &<G::Scalar as PrimeField>::Repr,
+    basis: &GlvBasis,
+    lambda: &G::Scalar
+) -> Option<[i128; 2]>
+{
+    let expected = G::Scalar::from_repr(*k).ok()?;
+    let limbs = k.as_ref();
+    let c1 = mul_shift_384(limbs, &basis.g1)?;
+    let c2 = mul_shift_384(limbs, &basis.g2)?;
+
+    // The true values are short, so computing modulo 2^128 is exact
+    let k_low = u128::from(limbs[0]) | (u128::from(limbs[1]) << 64);
+    let k1 = k_low.wrapping_sub(c1.wrapping_mul(basis.a1)).wrapping_sub(c2.wrapping_mul(basis.a2)) as i128;
+    let k2 = 0u128.wrapping_sub(c1.wrapping_mul(basis.b1)).wrapping_sub(c2.wrapping_mul(basis.b2)) as i128;
+
+    let mut check = signed_scalar::<G::Scalar>(k2);
+    check.mul_assign(lambda);
+    check.add_assign(&signed_scalar::<G::Scalar>(k1));
+    if check != expected {
+        return None;
+    }
+
+    Some([k1, k2])
+}
+
+/// Same as `dense_multiexp`, but using the GLV method: every scalar is split
+/// into two halves of about 128 bits, applied to the base and to its image
+/// under the endomorphism, so half as many regions are needed for twice the
+/// points. Scalars that cannot be split (non-canonical ones) go through the
+/// generic `dense_multiexp`. Use `EndomorphismParams::HAS_ENDOMORPHISM` to
+/// choose between this and the generic path.
+pub fn dense_multiexp_glv<G: HasEndomorphism>(
+    pool: &Worker,
+    bases: & [G],
+    exponents: & [<G::Scalar as PrimeField>::Repr]
+) -> Result<<G as CurveAffine>::Projective, SynthesisError>
+{
+    check_lengths(bases.len(), exponents.len())?;
+    let (beta, lambda, basis) = (G::beta(), G::lambda(), G::glv_basis());
+
+    let mut split_bases = vec![G::zero(); 2 * bases.len()];
+    let mut split_exponents = vec![<G::Scalar as PrimeField>::Repr::default(); 2 * bases.len()];
+    let chunk = pool.get_chunk_size(bases.len());
+    let mut unsplit = vec![vec![]; bases.chunks(chunk).len()];
+
+    pool.scope(bases.len(), |scope, chunk| {
+        for (i, ((((base, exp), split_base), split_exp), unsplit)) in bases.chunks(chunk)
+                    .zip(exponents.chunks(chunk))
+                    .zip(split_bases.chunks_mut(2 * chunk))
+                    .zip(split_exponents.chunks_mut(2 * chunk))
+                    .zip(unsplit.iter_mut())
+                    .enumerate() {
+            let (beta, lambda) = (&beta, &lambda);
+            scope.spawn(move |_| {
+                for (j, (base, exp)) in base.iter().zip(exp.iter()).enumerate() {
+                    let halves = match glv_decompose::<G>(exp, &basis, lambda) {
+                        Some(halves) => halves,
+                        None => {
+                            unsplit.push(i * chunk + j);
+                            continue;
+                        }
+                    };
+                    let points = [*base, base.endomorphism(beta)];
+                    for (h, (half, mut point)) in halves.iter().zip(points.iter().cloned()).enumerate() {
+                        if *half < 0 {
+                            point.negate();
+                        }
+                        let magnitude = half.unsigned_abs();
+                        split_base[2 * j + h] = point;
+                        split_exp[2 * j + h].as_mut()[0] = magnitude as u64;
+                        split_exp[2 * j + h].as_mut()[1] = (magnitude >> 64) as u64;
                     }
+                }
+            });
+        }
+    });
+
+    let c = cache_window_size::<G>(pool, split_bases.len());
+    let mut acc = G::Projective::zero();
+    for_each_region(pool, split_bases.len(), 128u32.div_ceil(c), |region, range| {
+        let skip = region * c;
+
+        dense_multiexp_chunk::<_, Limbs64>(&split_bases[range.clone()], &split_exponents[range], skip, c, skip == 0)
+    }, |_, partials| {
+        add_region(&mut acc, c, &partials);
+
+        Ok(())
+    })?;
+
+    let unsplit = unsplit.into_iter().flatten().collect::<Vec<_>>();
+    if !unsplit.is_empty() {
+        let bases = unsplit.iter().map(|i| bases[*i]).collect::<Vec<_>>();
+        let exponents = unsplit.iter().map(|i| exponents[*i]).collect::<Vec<_>>();
+        acc.add_assign(&dense_multiexp(pool, &bases, &exponents)?);
+    }
+
+    Ok(acc)
+}
+
+#[test]
+fn test_has_endomorphism() {
+    use crate::pairing::bn256::Bn256;
+    use crate::pairing::bls12_381::Bls12;
+    use crate::tests::dummy_engine::DummyEngine;
+
+    fn has_endomorphism<E: EndomorphismParams>() -> bool {
+        E::HAS_ENDOMORPHISM
+    }
+
+    assert!(has_endomorphism::<Bn256>());
+    assert!(has_endomorphism::<Bls12>());
+    assert!(!has_endomorphism::<DummyEngine>());
+}
+
+#[test]
+fn test_new_multiexp_with_bls12() {
+    fn naive_multiexp<G: CurveAffine>(
+        bases: Arc<Vec<G>>,
+        exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>
+    ) -> G::Projective
+    {
+        assert_eq!(bases.len(), exponents.len());
+
+        let mut acc = G::Projective::zero();
+
+        for (base, exp) in bases.iter().zip(exponents.iter()) {
+            acc.add_assign(&base.mul(*exp));
+        }
+
+        acc
+    }
+
+    use rand::{self, Rand};
+    use crate::pairing::bls12_381::Bls12;
+
+    use self::futures::executor::block_on;
+
+    const SAMPLES: usize = 1 << 14;
+
+    let rng = &mut rand::thread_rng();
+    let v = Arc::new((0..SAMPLES).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let naive = naive_multiexp(g.clone(), v.clone());
+
+    let pool = Worker::new();
+
+    let fast = block_on(
+        multiexp(
+            &pool,
+            (g, 0),
+            FullDensity,
+            v
+        )
+    ).unwrap();
+
+    assert_eq!(naive, fast);
+}
+
+#[test]
+#[ignore]
+fn test_new_multexp_speed_with_bn256() {
+    use rand::{self, Rand};
+    use crate::pairing::bn256::Bn256;
+    use num_cpus;
+
+    let cpus = num_cpus::get();
+    const SAMPLES: usize = 1 << 22;
+
+    let rng = &mut rand::thread_rng();
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+
+    use self::futures::executor::block_on;
+
+    let start = std::time::Instant::now();
+
+    let _fast = block_on(
+        multiexp(
+            &pool,
+            (g, 0),
+            FullDensity,
+            v
+        )
+    ).unwrap();
+
+
+    let duration_ns = start.elapsed().as_nanos() as f64;
+    println!("Elapsed {} ns for {} samples", duration_ns, SAMPLES);
+    let time_per_sample = duration_ns/(SAMPLES as f64);
+    println!("Tested on {} samples on {} CPUs with {} ns per multiplication", SAMPLES, cpus, time_per_sample);
+}
+
+
+#[test]
+fn test_dense_multiexp_vs_new_multiexp() {
+    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+    use crate::pairing::bn256::Bn256;
+    use num_cpus;
+
+    // const SAMPLES: usize = 1 << 22;
+    const SAMPLES: usize = 1 << 16;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    println!("Done generating test points and scalars");
+
+    let pool = Worker::new();
+
+    let start = std::time::Instant::now();
+
+    let dense = dense_multiexp(
+        &pool, &g, &v.clone()).unwrap();
+
+    let duration_ns = start.elapsed().as_nanos() as f64;
+    println!("{} ns for dense for {} samples", duration_ns, SAMPLES);
+
+    use self::futures::executor::block_on;
+
+    let start = std::time::Instant::now();
+
+    let sparse = block_on(
+        multiexp(
+            &pool,
+            (Arc::new(g), 0),
+            FullDensity,
+            Arc::new(v)
+        )
+    ).unwrap();
+
+    let duration_ns = start.elapsed().as_nanos() as f64;
+    println!("{} ns for sparse for {} samples", duration_ns, SAMPLES);
+
+    assert_eq!(dense, sparse);
+}
+#[test]
+fn test_parallel_bucket_summation() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    for &len in [1usize, 7, 1 << 10].iter() {
+        let buckets = (0..len).map(|_| <Bn256 as Engine>::G1::rand(rng)).collect::<Vec<_>>();
+
+        assert_eq!(serial_sum_buckets(&buckets), parallel_sum_buckets(&pool, &buckets));
+    }
+}
+
+#[test]
+#[ignore]
+fn test_parallel_bucket_summation_speed() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    for &c in [12u32, 16, 20].iter() {
+        let buckets = (0..(1 << c) - 1).map(|_| <Bn256 as Engine>::G1::rand(rng)).collect::<Vec<_>>();
+
+        let start = std::time::Instant::now();
+        let serial = serial_sum_buckets(&buckets);
+        println!("c = {}: serial summation taken {:?}", c, start.elapsed());
+
+        let start = std::time::Instant::now();
+        let parallel = parallel_sum_buckets(&pool, &buckets);
+        println!("c = {}: parallel summation taken {:?}", c, start.elapsed());
+
+        assert_eq!(serial, parallel);
+    }
+}
+
+#[test]
+fn test_multiexp_split() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    for &split_at in [0, 1, 333, SAMPLES / 2, SAMPLES].iter() {
+        let (left, right) = multiexp_split(&pool, &g, &v, split_at).unwrap();
+
+        assert_eq!(left, dense_multiexp(&pool, &g[..split_at], &v[..split_at]).unwrap());
+        assert_eq!(right, dense_multiexp(&pool, &g[split_at..], &v[split_at..]).unwrap());
+    }
+
+    match multiexp_split(&pool, &g, &v, SAMPLES + 1) {
+        Err(SynthesisError::SplitOutOfBounds { split_at, len }) => assert_eq!((split_at, len), (SAMPLES + 1, SAMPLES)),
+        _ => panic!("split past the end accepted")
+    }
+}
+
+#[test]
+fn test_streaming_multiexp() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = STREAMING_BATCH_SIZE * 2 + 123;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+
+    let streamed = streaming_multiexp(&pool, (g.clone(), 0), v.iter().cloned(), SAMPLES).unwrap();
+    let full = multiexp(&pool, (g.clone(), 0), FullDensity, Arc::new(v.clone())).wait().unwrap();
+
+    assert_eq!(streamed, full);
+
+    assert!(streaming_multiexp(&pool, (g, 0), v.iter().cloned().take(SAMPLES - 1), SAMPLES).is_err());
+}
+
+#[test]
+fn test_multiexp_length_mismatch() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 16;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    match dense_multiexp(&pool, &g[1..], &v) {
+        Err(SynthesisError::LengthMismatch { bases, exponents }) => {
+            assert_eq!((bases, exponents), (SAMPLES - 1, SAMPLES));
+        },
+        _ => panic!("expected a length mismatch")
+    }
+
+    match multiexp_split(&pool, &g, &v[1..], 0) {
+        Err(SynthesisError::LengthMismatch { bases, exponents }) => {
+            assert_eq!((bases, exponents), (SAMPLES, SAMPLES - 1));
+        },
+        _ => panic!("expected a length mismatch")
+    }
+
+    let mut density = DensityTracker::new();
+    for i in 0..(SAMPLES - 1) {
+        density.add_element();
+        density.inc(i);
+    }
+
+    match multiexp(&pool, (Arc::new(g.clone()), 0), Arc::new(density), Arc::new(v.clone())).wait() {
+        Err(SynthesisError::LengthMismatch { bases, exponents }) => {
+            assert_eq!((bases, exponents), (SAMPLES - 1, SAMPLES));
+        },
+        _ => panic!("expected a length mismatch")
+    }
+
+    match streaming_multiexp(&pool, (Arc::new(g), 0), v, SAMPLES + 1) {
+        Err(SynthesisError::LengthMismatch { bases, exponents }) => {
+            assert_eq!((bases, exponents), (SAMPLES + 1, SAMPLES));
+        },
+        _ => panic!("expected a length mismatch")
+    }
+}
+
+#[cfg(feature = "experimental")]
+#[test]
+fn test_multiexp_interleaved_g1_g2() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bls12_381::Bls12;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g1 = (0..SAMPLES).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let g2 = (0..SAMPLES).map(|_| <Bls12 as Engine>::G2::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let (g1_result, g2_result) = multiexp_interleaved_g1_g2(&pool, &g1, &g2, &v).unwrap();
+
+    assert_eq!(g1_result, dense_multiexp(&pool, &g1, &v).unwrap());
+    assert_eq!(g2_result, dense_multiexp(&pool, &g2, &v).unwrap());
+
+    let g2_long = (0..SAMPLES + 2).map(|_| <Bls12 as Engine>::G2::rand(rng).into_affine()).collect::<Vec<_>>();
+    match multiexp_interleaved_g1_g2(&pool, &g1, &g2_long, &v) {
+        Err(SynthesisError::LengthMismatch { bases, exponents }) => {
+            assert_eq!(bases, SAMPLES + 2);
+            assert_eq!(exponents, SAMPLES);
+        },
+        _ => panic!("expected LengthMismatch"),
+    }
+}
+
+#[test]
+fn test_dense_multiexp_u64() {
+    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let pool = Worker::new();
+
+    for &max_bits in [0u32, 1, 5, 16, 64].iter() {
+        let s = (0..SAMPLES).map(|_| if max_bits == 64 { rng.gen::<u64>() } else { rng.gen::<u64>() % (1u64 << max_bits) }).collect::<Vec<_>>();
+
+        let mut naive = <Bn256 as Engine>::G1::zero();
+        for (base, &scalar) in g.iter().zip(s.iter()) {
+            let scalar = <Bn256 as ScalarEngine>::Fr::from_repr(scalar.into()).unwrap();
+            naive.add_assign(&base.mul(scalar.into_repr()));
+        }
+
+        assert_eq!(naive, dense_multiexp_u64(&pool, &g, &s).unwrap());
+    }
+}
+
+#[test]
+fn test_multiexp_const() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 100;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut v = [<Bn256 as ScalarEngine>::Fr::zero().into_repr(); SAMPLES];
+    let mut g = [<Bn256 as Engine>::G1Affine::zero(); SAMPLES];
+    for (v, g) in v.iter_mut().zip(g.iter_mut()) {
+        *v = <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr();
+        *g = <Bn256 as Engine>::G1::rand(rng).into_affine();
+    }
+
+    let pool = Worker::new();
+
+    assert_eq!(
+        multiexp_const::<_, SAMPLES, 4, 15>(&pool, &g, &v).unwrap(),
+        dense_multiexp_inner::<_, Limbs64>(&pool, &g, &v, 0, 4, true).unwrap()
+    );
+    assert_eq!(
+        multiexp_const::<_, SAMPLES, 7, 127>(&pool, &g, &v).unwrap(),
+        dense_multiexp_inner::<_, Limbs64>(&pool, &g, &v, 0, 7, true).unwrap()
+    );
+    // the widest window still fits the stack of a worker thread
+    assert_eq!(
+        multiexp_const::<_, SAMPLES, MAX_CONST_WINDOW_SIZE, 1023>(&pool, &g, &v).unwrap(),
+        dense_multiexp_inner::<_, Limbs64>(&pool, &g, &v, 0, MAX_CONST_WINDOW_SIZE, true).unwrap()
+    );
+    assert_eq!(
+        multiexp_const::<_, SAMPLES, 7, 127>(&Worker::with_cpus(1), &g, &v).unwrap(),
+        dense_multiexp_inner::<_, Limbs64>(&pool, &g, &v, 0, 7, true).unwrap()
+    );
+}
+
+#[cfg(all(feature = "perf-counters", target_os = "linux"))]
+#[test]
+fn test_multiexp_with_perf_counters() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let (result, stats) = multiexp_with_perf_counters(&pool, &g, &v).unwrap();
+    assert_eq!(result, dense_multiexp(&pool, &g, &v).unwrap());
+
+    // either counters are unavailable, or some work has been counted
+    assert!(stats == crate::perf::PerfStats::default() || stats.instructions > 0);
+}
+
+#[test]
+fn test_multiexp_iterator() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    // exercise the trivial exponent path as well
+    v[0] = <Bn256 as ScalarEngine>::Fr::one().into_repr();
+
+    let pool = Worker::new();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+
+    let mut iter = MultiexpIterator::new(&g, &v).unwrap();
+    let steps = iter.remaining_steps();
+    assert_eq!(iter.by_ref().count(), steps);
+    assert_eq!(iter.finish(), expected);
+
+    // finishing early runs the remaining steps
+    let mut iter = MultiexpIterator::new(&g, &v).unwrap();
+    iter.next();
+    assert_eq!(iter.finish(), expected);
+}
+
+#[test]
+fn test_struct_of_arrays_bases() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let soa = StructOfArraysBases::from(g.clone());
+    assert_eq!(soa.len(), SAMPLES);
+    assert_eq!(soa.get(17), g[17]);
+
+    let pool = Worker::new();
+
+    let expected = multiexp(&pool, (Arc::new(g), 0), FullDensity, v.clone()).wait().unwrap();
+    let result = multiexp(&pool, (soa, 0), FullDensity, v).wait().unwrap();
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_multiexp_repeated_base() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    // with a single base every scalar contends for the buckets of the same
+    // point, and the result must be the sum of scalars (mod r) times the base
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let scalars = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng)).collect::<Vec<_>>();
+    let p = <Bn256 as Engine>::G1::rand(rng).into_affine();
+
+    let mut sum = <Bn256 as ScalarEngine>::Fr::zero();
+    for s in scalars.iter() {
+        sum.add_assign(s);
+    }
+    let expected = p.mul(sum.into_repr());
+
+    let v = scalars.iter().map(|s| s.into_repr()).collect::<Vec<_>>();
+    let g = vec![p; SAMPLES];
+
+    let pool = Worker::new();
+
+    assert_eq!(multiexp(&pool, (Arc::new(g.clone()), 0), FullDensity, Arc::new(v.clone())).wait().unwrap(), expected);
+    assert_eq!(dense_multiexp(&pool, &g, &v).unwrap(), expected);
+    assert_eq!(streaming_multiexp(&pool, (Arc::new(g.clone()), 0), v.iter().cloned(), SAMPLES).unwrap(), expected);
+    assert_eq!(MultiexpIterator::new(&g, &v).unwrap().finish(), expected);
+
+    let (mut left, right) = multiexp_split(&pool, &g, &v, SAMPLES / 3).unwrap();
+    left.add_assign(&right);
+    assert_eq!(left, expected);
+
+    let mut g_arr = [p; 100];
+    let mut v_arr = [v[0]; 100];
+    g_arr.copy_from_slice(&g[..100]);
+    v_arr.copy_from_slice(&v[..100]);
+    let mut sum = <Bn256 as ScalarEngine>::Fr::zero();
+    for s in scalars[..100].iter() {
+        sum.add_assign(s);
+    }
+    assert_eq!(multiexp_const::<_, 100, 5, 31>(&pool, &g_arr, &v_arr).unwrap(), p.mul(sum.into_repr()));
+
+    let small = (0..SAMPLES as u64).map(|i| i * 1000 + 7).collect::<Vec<_>>();
+    let total = small.iter().sum::<u64>();
+    assert_eq!(dense_multiexp_u64(&pool, &g, &small).unwrap(), p.mul(total));
+}
+
+#[test]
+fn test_multiexp_batch() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+    use self::futures::stream::StreamExt;
+
+    const JOBS: usize = 8;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let pool = Worker::new();
+
+    let mut jobs = vec![];
+    let mut expected = vec![];
+    for i in 0..JOBS {
+        let samples = 1 << (4 + i);
+        let v = Arc::new((0..samples).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+        let g = Arc::new((0..samples).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+        expected.push(multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap());
+        jobs.push(MultiexpJob { bases: (g, 0), exponents: v });
+    }
+
+    let results = block_on(multiexp_batch(&pool, jobs).collect::<Vec<_>>());
+    assert_eq!(results.len(), JOBS);
+
+    let mut seen = [false; JOBS];
+    for (idx, result) in results {
+        assert!(!seen[idx], "job {} yielded twice", idx);
+        seen[idx] = true;
+        assert_eq!(result.unwrap(), expected[idx]);
+    }
+}
+
+#[test]
+fn test_multiexp_with_trace() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let mut g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let (result, trace) = multiexp_with_trace(&pool, &g, &v).unwrap();
+    assert_eq!(result, dense_multiexp(&pool, &g, &v).unwrap());
+
+    // the trace does not depend on the order of the pairs
+    v.reverse();
+    g.reverse();
+    assert_eq!(multiexp_with_trace(&pool, &g, &v).unwrap(), (result, trace));
+
+    // nor on how the regions are split over the worker
+    assert_eq!(multiexp_with_trace(&Worker::with_cpus(4), &g, &v).unwrap(), (result, trace));
+
+    // but changes with the exponents
+    v[0] = <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr();
+    assert_ne!(multiexp_with_trace(&pool, &g, &v).unwrap().1, trace);
+}
+
+#[test]
+fn test_multiexp_partial_last_region() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 6;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng)).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    // set the topmost scalar bits, which land in the narrow region
+    v[0] = <Bn256 as ScalarEngine>::Fr::one();
+    v[0].negate();
+    v[1] = <Bn256 as ScalarEngine>::Fr::one();
+
+    let mut expected = <Bn256 as Engine>::G1::zero();
+    for (g, v) in g.iter().zip(v.iter()) {
+        expected.add_assign(&g.mul(v.into_repr()));
+    }
+
+    let v = v.iter().map(|v| v.into_repr()).collect::<Vec<_>>();
+    let shared_g = Arc::new(g.clone());
+    let shared_v = Arc::new(v.clone());
+
+    let pool = Worker::new();
+
+    let num_bits = <Bn256 as ScalarEngine>::Fr::NUM_BITS;
+    for c in 3..=13 {
+        if num_bits % c == 0 {
+            continue;
+        }
+
+        assert_eq!(dense_multiexp_inner::<_, Limbs64>(&pool, &g, &v, 0, c, true).unwrap(), expected, "dense, c = {}", c);
+        assert_eq!(
+            multiexp_with_window(&pool, (shared_g.clone(), 0), FullDensity, shared_v.clone(), c).wait().unwrap(),
+            expected,
+            "source based, c = {}", c
+        );
+    }
+}
+
+#[test]
+fn test_multiexp_with_temporary_cpus() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+    let chunk_size = pool.get_chunk_size(SAMPLES);
+
+    for &cpus in [1, 3, 64].iter() {
+        let (result, source_based) = pool.with_temporary_cpus(cpus, |w| {
+            (
+                dense_multiexp(w, &g, &v).unwrap(),
+                multiexp(w, (Arc::new(g.clone()), 0), FullDensity, Arc::new(v.clone())).wait().unwrap()
+            )
+        });
+        assert_eq!(result, expected);
+        assert_eq!(source_based, expected);
+    }
+
+    assert_eq!(pool.get_chunk_size(SAMPLES), chunk_size);
+}
+
+#[test]
+fn test_normalize_batch_for_pairing() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut g1 = (0..7).map(|_| <Bn256 as Engine>::G1::rand(rng)).collect::<Vec<_>>();
+    let g2 = (0..3).map(|_| <Bn256 as Engine>::G2::rand(rng)).collect::<Vec<_>>();
+    g1.push(<Bn256 as Engine>::G1::zero());
+
+    let pool = Worker::new();
+    let (g1_affine, g2_affine) = normalize_batch_for_pairing::<Bn256>(&pool, &g1, &g2);
+
+    assert_eq!(g1_affine, g1.iter().map(|p| p.into_affine()).collect::<Vec<_>>());
+    assert_eq!(g2_affine, g2.iter().map(|p| p.into_affine()).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_multiexp_normalized() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 12;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let results = [1, 2, 8].iter().map(|&cpus| {
+        let pool = Worker::new_with_cpus(cpus);
+        multiexp_normalized(&pool, (g.clone(), 0), FullDensity, v.clone()).unwrap()
+    }).collect::<Vec<_>>();
+
+    // compare the raw coordinates, not just the points
+    let raw = format!("{:?}", results[0]);
+    for result in results.iter() {
+        assert!(result.is_normalized());
+        assert_eq!(format!("{:?}", result), raw);
+    }
+
+    let pool = Worker::new();
+    assert_eq!(results[0], multiexp(&pool, (g, 0), FullDensity, v).wait().unwrap());
+}
+
+#[test]
+fn test_memoized_multiexp() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 8;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let mut cache = MemoizedMultiexp::new(2);
+
+    let queries = [(0, SAMPLES), (0, SAMPLES / 2), (SAMPLES / 4, SAMPLES), (0, SAMPLES), (0, SAMPLES / 2)];
+    for &(start, end) in queries.iter() {
+        let expected = dense_multiexp(&pool, &g[start..end], &v[start..end]).unwrap();
+
+        // a miss on the first query, a hit on the second one
+        assert_eq!(cache.multiexp(&pool, &g[start..end], &v[start..end]).unwrap(), expected);
+        assert_eq!(cache.multiexp(&pool, &g[start..end], &v[start..end]).unwrap(), expected);
+        assert!(cache.len() <= 2);
+    }
+
+    // same bases with different exponents must not hit
+    let mut w = v.clone();
+    w[3] = <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr();
+    assert_eq!(cache.multiexp(&pool, &g, &w).unwrap(), dense_multiexp(&pool, &g, &w).unwrap());
+}
+
+#[test]
+fn test_limb_views() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+    use crate::pairing::bls12_381::Bls12;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..32).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    for repr in v.iter() {
+        for c in 1..=32 {
+            for skip in 0..(<Bn256 as ScalarEngine>::Fr::NUM_BITS + c) {
+                let mut shifted = *repr;
+                shifted.shr(skip);
+                let expected = shifted.as_ref()[0] & ((1u64 << c) - 1);
+
+                assert_eq!(Limbs64::window(repr, skip, c), expected, "64-bit view, skip = {}, c = {}", skip, c);
+                assert_eq!(Limbs32::window(repr, skip, c), expected, "32-bit view, skip = {}, c = {}", skip, c);
+            }
+        }
+    }
+
+    let pool = Worker::new();
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    assert_eq!(
+        dense_multiexp_with_limbs::<_, Limbs32>(&pool, &g, &v).unwrap(),
+        dense_multiexp_with_limbs::<_, Limbs64>(&pool, &g, &v).unwrap()
+    );
+
+    let v = (0..SAMPLES).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    assert_eq!(
+        dense_multiexp_with_limbs::<_, Limbs32>(&pool, &g, &v).unwrap(),
+        dense_multiexp_with_limbs::<_, Limbs64>(&pool, &g, &v).unwrap()
+    );
+}
+
+#[test]
+fn test_dense_multiexp_detect_sparse() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let zero = <Bn256 as ScalarEngine>::Fr::zero().into_repr();
+
+    let pool = Worker::new();
+
+    let mut v = vec![zero; SAMPLES];
+    assert!(dense_multiexp_detect_sparse(&pool, &g, &v).unwrap().is_zero());
+
+    for &k in [0, 1, SAMPLES / 2 + 3, SAMPLES - 1].iter() {
+        let scalar = <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr();
+        v[k] = scalar;
+        assert_eq!(dense_multiexp_detect_sparse(&pool, &g, &v).unwrap(), g[k].mul(scalar));
+        assert_eq!(dense_multiexp_detect_sparse(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+        v[k] = zero;
+    }
+
+    // two nonzero entries in the same chunk and in different chunks
+    v[3] = <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr();
+    v[4] = <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr();
+    assert_eq!(dense_multiexp_detect_sparse(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+    v[4] = zero;
+    v[SAMPLES - 2] = <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr();
+    assert_eq!(dense_multiexp_detect_sparse(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    assert_eq!(dense_multiexp_detect_sparse(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+}
+
+#[test]
+fn test_multiexp_from_digit_matrix() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+    let num_bits = <Bn256 as ScalarEngine>::Fr::NUM_BITS;
+
+    for &c in [1, 7, 10, 16].iter() {
+        let digits = DigitMatrix::from_exponents(&v, num_bits, c).unwrap();
+
+        let mut encoded = vec![];
+        digits.write(&mut encoded).unwrap();
+        assert_eq!(encoded.len(), 20 + 2 * digits.num_regions() * SAMPLES);
+        let decoded = DigitMatrix::read(&encoded[..]).unwrap();
+        assert_eq!(decoded, digits);
+
+        assert_eq!(multiexp_from_digit_matrix(&pool, &g, &decoded).unwrap(), expected);
+    }
+
+    // a digit outside of the window is rejected
+    let digits = DigitMatrix::from_exponents(&v, num_bits, 4).unwrap();
+    let mut encoded = vec![];
+    digits.write(&mut encoded).unwrap();
+    encoded[20] = 0x10;
+    assert!(DigitMatrix::read(&encoded[..]).is_err());
+
+    // so is a header with more regions than any scalar has, even without digits
+    let mut header = DigitMatrix::MAGIC.to_vec();
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&4_000_000u32.to_le_bytes());
+    header.extend_from_slice(&0u64.to_le_bytes());
+    assert_eq!(DigitMatrix::read(&header[..]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    // and a matrix covering more bits than the scalar field
+    let digits = DigitMatrix::from_exponents(&v, MAX_DIGIT_MATRIX_BITS, 1).unwrap();
+    match multiexp_from_digit_matrix(&pool, &g, &digits) {
+        Err(SynthesisError::InvalidWindow(1)) => {},
+        _ => panic!("expected InvalidWindow")
+    }
+}
+
+#[test]
+fn test_multiexp_with_opening_data() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    v[0] = <Bn256 as ScalarEngine>::Fr::one().into_repr();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let (result, data) = multiexp_with_opening_data(&pool, &g, &v).unwrap();
+    assert_eq!(result, dense_multiexp(&pool, &g, &v).unwrap());
+    assert_eq!(data.result(), result);
+
+    // the buckets recovered from the running sums hold the bases of their digit
+    let c = data.c();
+    let digits = DigitMatrix::from_exponents(&v, <Bn256 as ScalarEngine>::Fr::NUM_BITS, c).unwrap();
+    assert_eq!(data.num_regions(), digits.num_regions());
+    for &region in [0, data.num_regions() - 1].iter() {
+        assert_eq!(data.running_sums(region).unwrap().len(), (1 << c) - 1);
+        for digit in 1..(1 << c) {
+            let mut expected = <Bn256 as Engine>::G1::zero();
+            for (base, &d) in g.iter().zip(digits.region(region).iter()) {
+                if d as usize == digit {
+                    expected.add_assign_mixed(base);
+                }
+            }
+            assert_eq!(data.bucket(region, digit), Some(expected));
+        }
+        assert_eq!(data.bucket(region, 0), None);
+        assert_eq!(data.bucket(region, 1 << c), None);
+    }
+    assert_eq!(data.bucket(data.num_regions(), 1), None);
+    assert_eq!(data.running_sums(data.num_regions()), None);
+
+    // the buckets of a region split over several chunks are merged back
+    assert_eq!(multiexp_with_opening_data(&Worker::with_cpus(4), &g, &v).unwrap(), (result, data));
+}
+
+#[test]
+fn test_dense_multiexp_adaptive() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+    let num_bits = <Bn256 as ScalarEngine>::Fr::NUM_BITS;
+
+    for &samples in [0, 1, 17, 1 << 10, 1 << 13].iter() {
+        let v = (0..samples).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+        let g = (0..samples).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+        // windows are contiguous and cover all the bits
+        let schedule = adaptive_schedule(&v, num_bits);
+        let mut next = 0;
+        for &(skip, c) in schedule.iter() {
+            assert_eq!(skip, next);
+            assert!((1..=MAX_ADAPTIVE_WINDOW).contains(&c));
+            next += c;
+        }
+        assert!(next >= num_bits);
+
+        assert_eq!(dense_multiexp_adaptive(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+    }
+
+    // small scalars leave the upper windows empty, which should make them wide
+    let v = (0..(1 << 10)).map(|i| <Bn256 as ScalarEngine>::Fr::from_str(&(i * 7919).to_string()).unwrap().into_repr()).collect::<Vec<_>>();
+    let g = (0..(1 << 10)).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    assert!(adaptive_schedule(&v, num_bits).len() < num_bits.div_ceil(window_size(v.len())) as usize);
+    assert_eq!(dense_multiexp_adaptive(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+}
+
+#[test]
+#[ignore]
+fn test_dense_multiexp_adaptive_speed() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 20;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let start = std::time::Instant::now();
+    let uniform = dense_multiexp(&pool, &g, &v).unwrap();
+    println!("Uniform window taken {:?}", start.elapsed());
+
+    let start = std::time::Instant::now();
+    let adaptive = dense_multiexp_adaptive(&pool, &g, &v).unwrap();
+    println!("Adaptive windows taken {:?}", start.elapsed());
+
+    assert_eq!(uniform, adaptive);
+}
+
+#[test]
+fn test_dense_multiexp_cancellable() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let flag = Arc::new(AtomicBool::new(false));
+    assert_eq!(dense_multiexp_cancellable(&pool, &g, &v, &flag).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+
+    flag.store(true, Ordering::Relaxed);
+    match dense_multiexp_cancellable(&pool, &g, &v, &flag) {
+        Err(SynthesisError::Cancelled) => {},
+        _ => panic!("expected cancellation")
+    }
+
+    #[cfg(feature = "tokio-cancellation")]
+    {
+        let token = tokio_util::sync::CancellationToken::new();
+        assert_eq!(dense_multiexp_cancellable(&pool, &g, &v, &token).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+
+        token.child_token().cancel();
+        assert!(dense_multiexp_cancellable(&pool, &g, &v, &token).is_ok());
+
+        token.cancel();
+        match dense_multiexp_cancellable(&pool, &g, &v, &token) {
+            Err(SynthesisError::Cancelled) => {},
+            _ => panic!("expected cancellation")
+        }
+    }
+}
+
+#[test]
+fn test_raw_affine_source() {
+    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+
+    // bases only exist where the density map is set
+    let mut density = DensityTracker::new();
+    let mut g = vec![];
+    let mut dense_v = vec![];
+    for i in 0..SAMPLES {
+        density.add_element();
+        if rng.gen() {
+            density.inc(i);
+            g.push(<Bn256 as Engine>::G1::rand(rng).into_affine());
+            dense_v.push(v[i]);
+        }
+    }
+    let density = Arc::new(density);
+    let g = Arc::new(g);
+
+    let pool = Worker::new();
+
+    // random points are valid and finite, and the density covers all of them
+    let raw = RawAffineSource::from_trusted(g.clone());
+
+    let expected = multiexp(&pool, (g.clone(), 0), density.clone(), v.clone()).wait().unwrap();
+    assert_eq!(multiexp(&pool, raw, density.clone(), v.clone()).wait().unwrap(), expected);
+    assert_eq!(dense_multiexp(&pool, &g, &dense_v).unwrap(), expected);
+
+    // a density map asking for more bases than there are is an error
+    let short = RawAffineSource::from_trusted(Arc::new(g[1..].to_vec()));
+    assert!(matches!(multiexp(&pool, short, density, v).wait(), Err(SynthesisError::IoError(_))));
+}
+
+#[test]
+fn test_dense_multiexp_with_window() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 8;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+
+    assert_eq!(dense_multiexp_with_window(&pool, &g, &v, 1, DEFAULT_MAX_REGIONS).unwrap(), expected);
+    assert_eq!(dense_multiexp_with_window(&pool, &g, &v, 5, DEFAULT_MAX_REGIONS).unwrap(), expected);
+
+    for &(c, max_regions) in [(0, DEFAULT_MAX_REGIONS), (1, 64), (3, 84), (MAX_WINDOW_SIZE + 1, DEFAULT_MAX_REGIONS)].iter() {
+        match dense_multiexp_with_window(&pool, &g, &v, c, max_regions) {
+            Err(SynthesisError::InvalidWindow(rejected)) => assert_eq!(rejected, c),
+            _ => panic!("expected window {} to be rejected", c)
+        }
+    }
+    assert!(dense_multiexp_with_window(&pool, &g, &v, 3, 85).is_ok());
+}
+
+#[test]
+fn test_dense_multiexp_with_efficiency() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 12;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let (result, efficiency) = dense_multiexp_with_efficiency(&pool, &g, &v).unwrap();
+    assert_eq!(result, dense_multiexp(&pool, &g, &v).unwrap());
+    assert!(efficiency > 0f64 && efficiency <= 1f64, "efficiency {}", efficiency);
+}
+
+#[test]
+fn test_multiexp_from_readers() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let mut base_bytes = vec![];
+    for base in g.iter() {
+        base_bytes.extend_from_slice(base.into_uncompressed().as_ref());
+    }
+    let mut scalar_bytes = vec![];
+    for exp in v.iter() {
+        exp.write_be(&mut scalar_bytes).unwrap();
+    }
+
+    let pool = Worker::new();
+    let expected = multiexp(&pool, (Arc::new(g), 0), FullDensity, Arc::new(v)).wait().unwrap();
+
+    let result = multiexp_from_readers::<<Bn256 as Engine>::G1Affine, _, _>(&pool, &base_bytes[..], &scalar_bytes[..], SAMPLES);
+    assert_eq!(result.unwrap(), expected);
+
+    // truncated scalars
+    let result = multiexp_from_readers::<<Bn256 as Engine>::G1Affine, _, _>(&pool, &base_bytes[..], &scalar_bytes[1..], SAMPLES);
+    assert!(result.is_err());
+
+    // a base that is not on the curve
+    let mut corrupted = base_bytes.clone();
+    corrupted[64 * 5 + 63] ^= 1;
+    let result = multiexp_from_readers::<<Bn256 as Engine>::G1Affine, _, _>(&pool, &corrupted[..], &scalar_bytes[..], SAMPLES);
+    assert!(result.is_err());
+
+    // a scalar above the modulus
+    let mut corrupted = scalar_bytes.clone();
+    for byte in corrupted[32 * 7..32 * 8].iter_mut() {
+        *byte = 0xff;
+    }
+    let result = multiexp_from_readers::<<Bn256 as Engine>::G1Affine, _, _>(&pool, &base_bytes[..], &corrupted[..], SAMPLES);
+    assert!(result.is_err());
+
+    // a length whose byte size overflows
+    let result = multiexp_from_readers::<<Bn256 as Engine>::G1Affine, _, _>(&pool, &base_bytes[..], &scalar_bytes[..], usize::MAX);
+    match result {
+        Err(SynthesisError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+        _ => panic!("overflowing length accepted")
+    }
+
+    // a huge length is not allocated before the streams run out
+    let result = multiexp_from_readers::<<Bn256 as Engine>::G1Affine, _, _>(&pool, &base_bytes[..], &scalar_bytes[..], 1 << 40);
+    match result {
+        Err(SynthesisError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+        _ => panic!("truncated streams accepted")
+    }
+}
+
+#[test]
+fn test_multiexp_out() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 8;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let pool = Worker::new();
+
+    let mut jobs = vec![];
+    let mut expected = vec![];
+    for _ in 0..4 {
+        let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+        let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+        expected.push(multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap());
+        jobs.push(MultiexpJob { bases: (g, 0), exponents: v });
+    }
+
+    let projective: <Bn256 as Engine>::G1 = multiexp_out::<ProjectiveOutput, _, _, _, _>(
+        &pool, jobs[0].bases.clone(), FullDensity, jobs[0].exponents.clone()).unwrap();
+    assert_eq!(projective, expected[0]);
+
+    let affine: <Bn256 as Engine>::G1Affine = multiexp_out::<AffineOutput, _, _, _, _>(
+        &pool, jobs[0].bases.clone(), FullDensity, jobs[0].exponents.clone()).unwrap();
+    assert_eq!(affine, expected[0].into_affine());
+
+    let copies = jobs.iter().map(|job| MultiexpJob { bases: job.bases.clone(), exponents: job.exponents.clone() }).collect();
+    assert_eq!(multiexp_out_batch::<ProjectiveOutput, _, _>(&pool, copies).unwrap(), expected);
+
+    let affine = multiexp_out_batch::<AffineOutput, _, _>(&pool, jobs).unwrap();
+    assert_eq!(affine, expected.iter().map(|p| p.into_affine()).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_multiexp_affine_x() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 8;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+
+    let expected = multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap().into_affine();
+    let x = multiexp_affine_x(&pool, (g.clone(), 0), FullDensity, v).unwrap();
+    assert_eq!(x, expected.as_xy().0.into_repr());
+
+    let zeros = Arc::new(vec![<Bn256 as ScalarEngine>::Fr::zero().into_repr(); SAMPLES]);
+    match multiexp_affine_x(&pool, (g, 0), FullDensity, zeros) {
+        Err(SynthesisError::UnexpectedIdentity) => {},
+        _ => panic!("expected the identity to be rejected")
+    }
+}
+
+#[test]
+fn test_cache_window_size() {
+    use crate::pairing::bn256::Bn256;
+
+    let pool = Worker::new();
+    let bucket_size = std::mem::size_of::<<Bn256 as Engine>::G1>();
+
+    for &n in [1usize, 1 << 10, 1 << 16, 1 << 22, 1 << 26].iter() {
+        let c = cache_window_size::<<Bn256 as Engine>::G1Affine>(&pool, n);
+        assert!(c >= 1 && c <= window_size(n));
+        if let Some(l2) = pool.cache_sizes().l2 {
+            assert!(c == 1 || ((1usize << c) - 1) * bucket_size <= l2);
+        }
+    }
+}
+
+#[test]
+#[ignore]
+fn test_cache_window_size_speed() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 22;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    println!("Detected caches {:?}", pool.cache_sizes());
+
+    let c = window_size(SAMPLES);
+    let start = std::time::Instant::now();
+    let plain = dense_multiexp_with_window(&pool, &g, &v, c, DEFAULT_MAX_REGIONS).unwrap();
+    println!("Window of {} bits taken {:?}", c, start.elapsed());
+
+    let c = cache_window_size::<<Bn256 as Engine>::G1Affine>(&pool, SAMPLES);
+    let start = std::time::Instant::now();
+    let tuned = dense_multiexp(&pool, &g, &v).unwrap();
+    println!("Cache aware window of {} bits taken {:?}", c, start.elapsed());
+
+    assert_eq!(plain, tuned);
+}
+
+#[test]
+fn test_multiexp_dual_bases() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let h = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let (a, b) = multiexp_dual_bases(&pool, &g, &h, &v).unwrap();
+    let v = Arc::new(v);
+    assert_eq!(a, multiexp(&pool, (Arc::new(g.clone()), 0), FullDensity, v.clone()).wait().unwrap());
+    assert_eq!(b, multiexp(&pool, (Arc::new(h.clone()), 0), FullDensity, v.clone()).wait().unwrap());
+
+    assert!(multiexp_dual_bases(&pool, &g, &h[1..], &v).is_err());
+}
+
+#[test]
+fn test_multiexp_reduce_scalars() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::{Bn256, Fr};
+
+    const SAMPLES: usize = 1 << 8;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let mut v = (0..SAMPLES).map(|_| Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let canonical = v.clone();
+
+    // the order itself, the order plus k, and the largest representation
+    v[0] = Fr::char();
+    v[1] = Fr::char();
+    v[1].add_nocarry(&canonical[1]);
+    for limb in v[2].as_mut().iter_mut() {
+        *limb = u64::MAX;
+    }
+
+    let mut expected = canonical.clone();
+    expected[0] = Fr::zero().into_repr();
+    let mut max = Fr::from_str("2").unwrap().pow([256u64]);
+    max.sub_assign(&Fr::one());
+    expected[2] = max.into_repr();
+
+    assert_eq!(reduce_scalar::<Fr>(v[0]), expected[0]);
+    assert_eq!(reduce_scalar::<Fr>(v[1]), expected[1]);
+    assert_eq!(reduce_scalar::<Fr>(v[2]), expected[2]);
+    assert_eq!(reduce_scalar::<Fr>(v[3]), expected[3]);
+
+    let pool = Worker::new();
+    assert_eq!(multiexp_reduce_scalars(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &expected).unwrap());
+}
+
+#[cfg(feature = "profile")]
+#[test]
+fn test_dense_multiexp_with_stats() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 12;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    v[0] = <Bn256 as ScalarEngine>::Fr::one().into_repr();
+
+    let pool = Worker::new();
+
+    let (result, stats) = dense_multiexp_with_stats(&pool, &g, &v).unwrap();
+    assert_eq!(result, dense_multiexp(&pool, &g, &v).unwrap());
+    assert_eq!(stats.len() as u32, <Bn256 as ScalarEngine>::Fr::NUM_BITS.div_ceil(cache_window_size::<<Bn256 as Engine>::G1Affine>(&pool, SAMPLES)));
+
+    for (region, s) in stats.iter().enumerate() {
+        println!("region {}: fill {:?}, sum {:?}, combine {:?}", region, s.fill_time, s.sum_time, s.combine_time);
+    }
+    assert!(stats.iter().all(|s| s.fill_time > Duration::default()));
+}
+
+#[test]
+fn test_multiexp_approx() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let exact = multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap();
+
+    let c = window_size(SAMPLES);
+    let regions = 5;
+    let approx = multiexp_approx(&pool, (g.clone(), 0), FullDensity, v.clone(), regions).wait().unwrap();
+    assert!(approx != exact);
+
+    // The left out regions are the multiexp of the scalars shifted down by
+    // the covered bits, shifted back up.
+    let high = Arc::new(v.iter().map(|e| {
+        let mut e = *e;
+        e.shr(c * regions as u32);
+        e
+    }).collect::<Vec<_>>());
+    let mut rest = multiexp(&pool, (g.clone(), 0), FullDensity, high).wait().unwrap();
+    for _ in 0..c * regions as u32 {
+        rest.double();
+    }
+    rest.add_assign(&approx);
+    assert_eq!(rest, exact);
+
+    let all = multiexp_approx(&pool, (g, 0), FullDensity, v, usize::MAX).wait().unwrap();
+    assert_eq!(all, exact);
+}
+
+#[test]
+fn test_multiexp_executor() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 4;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let mut executor = MultiexpExecutor::new();
+
+    let expected = multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap();
+
+    for _ in 0..4 {
+        assert_eq!(executor.run(multiexp(&pool, (g.clone(), 0), FullDensity, v.clone())).unwrap(), expected);
+    }
+}
+
+#[test]
+#[ignore]
+fn test_multiexp_executor_speed() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 4;
+    const CALLS: usize = 2000;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let mut executor = MultiexpExecutor::new();
+
+    let start = Instant::now();
+    for _ in 0..CALLS {
+        multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap();
+    }
+    let waited = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..CALLS {
+        executor.run(multiexp(&pool, (g.clone(), 0), FullDensity, v.clone())).unwrap();
+    }
+    let executed = start.elapsed();
+
+    println!("{} tiny multiexps: wait {:?}, shared executor {:?}", CALLS, waited, executed);
+}
+
+#[test]
+fn test_multiexp_sparse() {
+    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 14;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let nonzero = (0..SAMPLES / 128).map(|_| {
+        (rng.gen_range(0, SAMPLES), <Bn256 as ScalarEngine>::Fr::rand(rng))
+    }).collect::<Vec<_>>();
+
+    let mut dense = vec![<Bn256 as ScalarEngine>::Fr::zero(); SAMPLES];
+    for (idx, exp) in nonzero.iter() {
+        dense[*idx].add_assign(exp);
+    }
+    let dense = Arc::new(dense.into_iter().map(|e| e.into_repr()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let nonzero = nonzero.into_iter().map(|(idx, exp)| (idx, exp.into_repr())).collect::<Vec<_>>();
+
+    let start = Instant::now();
+    let sparse = multiexp_sparse(&pool, &g, &nonzero).unwrap();
+    let sparse_time = start.elapsed();
+
+    let start = Instant::now();
+    let expected = multiexp(&pool, (Arc::new(g.clone()), 0), FullDensity, dense).wait().unwrap();
+    println!("sparse {:?}, dense {:?}", sparse_time, start.elapsed());
+
+    assert_eq!(sparse, expected);
+
+    assert!(multiexp_sparse(&pool, &g, &[(SAMPLES, nonzero[0].1)]).is_err());
+}
+
+#[test]
+fn test_validate_bases() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::{Bn256, G1Affine};
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let mut g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    g[3] = G1Affine::zero();
+
+    let pool = Worker::new();
+    validate_bases(&pool, &g).unwrap();
+    assert_eq!(dense_multiexp_checked(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+
+    for &corrupted in [SAMPLES - 1, SAMPLES / 2 + 1].iter() {
+        let (x, mut y) = g[corrupted].into_xy_unchecked();
+        y.add_assign(&Field::one());
+        g[corrupted] = G1Affine::from_xy_unchecked(x, y);
+
+        match validate_bases(&pool, &g) {
+            Err(SynthesisError::InvalidBase(idx)) => assert_eq!(idx, corrupted),
+            _ => panic!("corrupted base at {} not detected", corrupted)
+        }
+    }
+    assert!(dense_multiexp_checked(&pool, &g, &v).is_err());
+}
+
+#[test]
+fn test_multiexp_scoped() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let scoped = block_on(multiexp_scoped(&pool, &g, &v)).unwrap();
+    let expected = multiexp(&pool, (Arc::new(g), 0), FullDensity, Arc::new(v)).wait().unwrap();
+
+    assert_eq!(scoped, expected);
+}
+
+#[test]
+fn test_multiexp_and_negation() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng)).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let reprs = v.iter().map(|e| e.into_repr()).collect::<Vec<_>>();
+    let (result, negated) = multiexp_and_negation(&pool, &g, &reprs).unwrap();
+
+    let mut expected = result;
+    expected.negate();
+    assert_eq!(negated, expected);
+
+    let negated_reprs = v.iter().map(|e| {
+        let mut e = *e;
+        e.negate();
+        e.into_repr()
+    }).collect::<Vec<_>>();
+    assert_eq!(negated, dense_multiexp(&pool, &g, &negated_reprs).unwrap());
+}
+
+#[test]
+fn test_multiexp_multi_worker() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = (1 << 10) + 3;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+
+    for num_workers in 0..4 {
+        let workers = (0..num_workers).map(|_| Worker::new()).collect::<Vec<_>>();
+        assert_eq!(multiexp_multi_worker(&workers, &g, &v).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_check_window() {
+    assert_eq!(check_window(1 << 20, 16), WindowAdvice::Reasonable);
+    assert_eq!(check_window(1 << 20, window_size(1 << 20)), WindowAdvice::Reasonable);
+    assert_eq!(check_window(1 << 20, 22), WindowAdvice::TooLarge { suggested: 16 });
+    assert_eq!(check_window(1 << 20, 3), WindowAdvice::TooSmall { suggested: 16 });
+    assert_eq!(check_window(1 << 20, 0), WindowAdvice::TooSmall { suggested: 16 });
+
+    match check_window(16, 12) {
+        WindowAdvice::TooLarge { suggested } => assert!(suggested <= 4),
+        advice => panic!("expected too large, got {:?}", advice)
+    }
+}
+
+#[test]
+fn test_scalar_cache() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng)).collect::<Vec<_>>();
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let cache = ScalarCache::new(&pool, &v);
+    let reprs = v.iter().map(|e| e.into_repr()).collect::<Vec<_>>();
+
+    assert_eq!(*cache.reprs(), reprs);
+    assert!(Arc::ptr_eq(&cache.reprs(), &cache.reprs()));
+    assert_eq!(
+        multiexp(&pool, (g.clone(), 0), FullDensity, cache.reprs()).wait().unwrap(),
+        multiexp(&pool, (g, 0), FullDensity, Arc::new(reprs)).wait().unwrap()
+    );
+}
+
+#[test]
+fn test_multiexp_boolean() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 12;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let bits = (0..SAMPLES).map(|_| bool::rand(rng)).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let lifted = bits.iter().map(|bit| {
+        if *bit {
+            <Bn256 as ScalarEngine>::Fr::one().into_repr()
+        } else {
+            <Bn256 as ScalarEngine>::Fr::zero().into_repr()
+        }
+    }).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let start = Instant::now();
+    let result = multiexp_boolean(&pool, &g, &bits).unwrap();
+    let boolean_time = start.elapsed();
+
+    let start = Instant::now();
+    let expected = dense_multiexp(&pool, &g, &lifted).unwrap();
+    println!("boolean {:?}, dense {:?}", boolean_time, start.elapsed());
+
+    assert_eq!(result, expected);
+    assert!(multiexp_boolean(&pool, &g, &bits[1..]).is_err());
+}
+
+#[test]
+fn test_multiexp_with_window() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let expected = multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap();
+
+    for &c in [1, window_size(SAMPLES) + 2, 16].iter() {
+        assert_eq!(multiexp_with_window(&pool, (g.clone(), 0), FullDensity, v.clone(), c).wait().unwrap(), expected);
+    }
+    for &c in [0, MAX_WINDOW_SIZE + 1, 31, 32].iter() {
+        match multiexp_with_window(&pool, (g.clone(), 0), FullDensity, v.clone(), c).wait() {
+            Err(SynthesisError::InvalidWindow(invalid)) => assert_eq!(invalid, c),
+            _ => panic!("window of {} bits accepted", c)
+        }
+    }
+}
+
+#[test]
+fn test_multiexp_adversarial_inputs_do_not_panic() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use crate::pairing::bn256::{Bn256, G1Affine};
+
+    #[derive(Clone)]
+    struct PanickingSource;
+
+    impl SourceBuilder<G1Affine> for PanickingSource {
+        type Source = PanickingSource;
+
+        fn new(self) -> Self::Source {
+            panic!("source failed to build")
+        }
+    }
+
+    impl Source<G1Affine> for PanickingSource {
+        fn add_assign_mixed(&mut self, _: &mut <G1Affine as CurveAffine>::Projective) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+
+        fn skip(&mut self, _: usize) -> Result<(), SynthesisError> {
+            Ok(())
+        }
+    }
+
+    const SAMPLES: usize = 1 << 8;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+    let mut off_curve = (*g).clone();
+    let (x, mut y) = off_curve[1].into_xy_unchecked();
+    y.add_assign(&Field::one());
+    off_curve[1] = G1Affine::from_xy_unchecked(x, y);
+
+    let pool = Worker::new();
+
+    let results = catch_unwind(AssertUnwindSafe(|| vec![
+        multiexp(&pool, (Arc::new(g[1..].to_vec()), 0), FullDensity, v.clone()).wait(),
+        multiexp(&pool, PanickingSource, FullDensity, v.clone()).wait(),
+        multiexp_with_window(&pool, (g.clone(), 0), FullDensity, v.clone(), 40).wait(),
+        pool.with_temporary_cpus(0, |pool| multiexp(pool, (Arc::new(g[1..].to_vec()), 0), FullDensity, v.clone()).wait()),
+        dense_multiexp(&pool, &g[1..], &v),
+        dense_multiexp_with_window(&pool, &g, &v, 40, DEFAULT_MAX_REGIONS),
+        dense_multiexp_checked(&pool, &off_curve, &v),
+        multiexp_with_window(&pool, (g.clone(), 0), FullDensity, v.clone(), 31).wait(),
+        DigitMatrix::from_exponents(&v, 254, 0).map(|_| <Bn256 as Engine>::G1::zero()),
+        DigitMatrix::from_exponents_par(&pool, &v, 254, MAX_DIGIT_WINDOW_SIZE + 1).map(|_| <Bn256 as Engine>::G1::zero()),
+        multiexp_split(&pool, &g, &v, SAMPLES + 1).map(|(left, _)| left),
+    ])).expect("no panic escapes the multiexp entry points");
+
+    for (i, result) in results.iter().enumerate() {
+        assert!(result.is_err(), "adversarial input {} was accepted", i);
+    }
+    match results[1] {
+        Err(SynthesisError::WorkerFailure) => {},
+        _ => panic!("expected a worker failure")
+    }
+
+    // Inputs that are merely degenerate still succeed.
+    assert!(dense_multiexp::<G1Affine>(&pool, &[], &[]).unwrap().is_zero());
+    assert!(multiexp(&pool, (Arc::new(Vec::<G1Affine>::new()), 0), FullDensity, Arc::new(vec![])).wait().unwrap().is_zero());
+    pool.with_temporary_cpus(0, |pool| {
+        assert_eq!(dense_multiexp(pool, &g, &v).unwrap(), multiexp(pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap());
+    });
+}
+
+#[test]
+fn test_dense_multiexp_chunk_panic() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+
+    // Fail the first slice of bases of every region
+    INJECTED_CHUNK_PANIC.store(g.as_ptr() as usize, Ordering::SeqCst);
+    let result = dense_multiexp(&pool, &g, &v);
+    let scratch_result = dense_multiexp_with_scratch(&pool, &g, &v, &BucketScratch::new());
+    INJECTED_CHUNK_PANIC.store(0, Ordering::SeqCst);
+
+    for result in [result, scratch_result].iter() {
+        match result {
+            Err(SynthesisError::WorkerFailure) => {},
+            _ => panic!("expected a worker failure")
+        }
+    }
+    assert_eq!(dense_multiexp(&pool, &g, &v).unwrap(), expected);
+}
+
+#[test]
+fn test_multiexp_affine() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::{Bn256, G1Affine};
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap().into_affine();
+
+    assert_eq!(multiexp_affine(&pool, (g.clone(), 0), FullDensity, v.clone()).unwrap(), expected);
+    assert_eq!(dense_multiexp_affine(&pool, &g, &v).unwrap(), expected);
+    assert_eq!(block_on(multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).into_affine()).unwrap(), expected);
+
+    let zeros = Arc::new(vec![<Bn256 as ScalarEngine>::Fr::zero().into_repr(); SAMPLES]);
+    assert_eq!(multiexp_affine(&pool, (g, 0), FullDensity, zeros).unwrap(), G1Affine::zero());
+}
+
+#[test]
+fn test_chunks_joiner_cancel() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+    use std::sync::mpsc::{channel, Receiver};
+
+    // Every region reports progress one base before its end and then waits
+    // for the test to stop the multiexp, so none of them can complete
+    const SAMPLES: usize = PROGRESS_STEP + 1;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+
+    let start = || {
+        let (counts, reports) = channel();
+        let (resume, paused) = channel::<()>();
+        let paused = Mutex::new(paused);
+        let progress: ProgressCallback = Arc::new(move |processed, total| {
+            counts.send((processed, total)).unwrap();
+            let _ = paused.lock().unwrap().recv();
+        });
+        let joiner = multiexp_with_progress(&pool, (g.clone(), 0), FullDensity, v.clone(), progress);
+
+        (joiner, reports, resume)
+    };
+
+    // The callback is dropped once every region task is done, which ends the
+    // reports; none of them may account for all the bases
+    let check_stopped_early = |reports: Receiver<(usize, usize)>| {
+        for (processed, total) in reports.iter() {
+            assert!(processed < total);
+        }
+    };
+
+    let (joiner, reports, resume) = start();
+    let (processed, total) = reports.recv().unwrap();
+    assert_eq!((processed, total), (PROGRESS_STEP, SAMPLES * joiner.regions.len()));
+    joiner.cancel();
+    drop(resume);
+    match joiner.wait() {
+        Err(SynthesisError::Cancelled) => {},
+        _ => panic!("expected cancellation")
+    }
+    check_stopped_early(reports);
+
+    // Dropping the joiner cancels its regions the same way
+    let (joiner, reports, resume) = start();
+    assert_eq!(reports.recv().unwrap().0, PROGRESS_STEP);
+    drop(joiner);
+    drop(resume);
+    check_stopped_early(reports);
+
+    let small = Arc::new(v[..16].to_vec());
+    let expected = multiexp_serial((g.clone(), 0), FullDensity, &small).unwrap();
+    assert_eq!(multiexp(&pool, (g, 0), FullDensity, small).wait().unwrap(), expected);
+}
+
+#[test]
+fn test_multiexp_batch_with_shared_bases() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+    let exponents = [SAMPLES, SAMPLES / 2, SAMPLES].iter().map(|len| {
+        Arc::new((0..*len).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>())
+    }).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let joiners = multiexp_batch_with_shared_bases(&pool, (g.clone(), 0), FullDensity, &exponents);
+    assert_eq!(joiners.len(), exponents.len());
+
+    for (joiner, v) in joiners.into_iter().zip(exponents.iter()) {
+        assert_eq!(joiner.wait().unwrap(), multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap());
+    }
+}
+
+#[test]
+fn test_fixed_base_multiexp() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::{Bn256, G1Affine};
+
+    const SAMPLES: usize = 1 << 12;
+    const PROOFS: usize = 4;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    g[5] = G1Affine::zero();
+    let proofs = (0..PROOFS).map(|_| {
+        (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let start = Instant::now();
+    let fixed = FixedBaseMultiexp::new(&pool, &g, 12).unwrap();
+    let setup = start.elapsed();
+    assert_eq!(fixed.table_bytes(), SAMPLES * 22 * std::mem::size_of::<G1Affine>());
+
+    let start = Instant::now();
+    let results = proofs.iter().map(|v| fixed.multiexp(&pool, v).unwrap()).collect::<Vec<_>>();
+    let fixed_time = start.elapsed();
+
+    let start = Instant::now();
+    let expected = proofs.iter().map(|v| dense_multiexp(&pool, &g, v).unwrap()).collect::<Vec<_>>();
+    println!("{} proofs: fixed base {:?} after {:?} setup, dense {:?}", PROOFS, fixed_time, setup, start.elapsed());
+
+    assert_eq!(results, expected);
+
+    let small = FixedBaseMultiexp::new(&pool, &g, 5).unwrap();
+    assert_eq!(small.multiexp(&pool, &proofs[0]).unwrap(), expected[0]);
+    assert!(small.multiexp(&pool, &proofs[0][1..]).is_err());
+    assert!(FixedBaseMultiexp::new(&pool, &g, 0).is_err());
+}
+
+#[test]
+fn test_dense_multiexp_glv() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::{Bn256, G1Affine, Fr};
+
+    fn check_endomorphism<G: HasEndomorphism>(g: G) {
+        assert_eq!(g.endomorphism(&G::beta()).into_projective(), g.mul(G::lambda().into_repr()));
+    }
+
+    const SAMPLES: usize = 1 << 14;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    check_endomorphism(<Bn256 as Engine>::G1::rand(rng).into_affine());
+    check_endomorphism(<crate::pairing::bls12_381::Bls12 as Engine>::G1::rand(rng).into_affine());
+
+    let mut v = (0..SAMPLES).map(|_| Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let mut g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+    v[0] = Fr::zero().into_repr();
+    v[1] = Fr::one().into_repr();
+    v[2] = minus_one.into_repr();
+    v[3] = G1Affine::lambda().into_repr();
+    // not canonical, goes through the generic path
+    v[4] = Fr::char();
+    v[4].add_nocarry(&<Fr as PrimeField>::Repr::from(5));
+    assert!(glv_decompose::<G1Affine>(&v[4], &G1Affine::glv_basis(), &G1Affine::lambda()).is_none());
+    g[5] = G1Affine::zero();
+
+    let pool = Worker::new();
+
+    let start = Instant::now();
+    let glv = dense_multiexp_glv(&pool, &g, &v).unwrap();
+    let glv_time = start.elapsed();
+
+    let start = Instant::now();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+    println!("GLV {:?}, generic {:?}", glv_time, start.elapsed());
+
+    assert_eq!(glv, expected);
+
+    let bls_v = (0..SAMPLES / 16).map(|_| <crate::pairing::bls12_381::Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let bls_g = (0..SAMPLES / 16).map(|_| <crate::pairing::bls12_381::Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    assert_eq!(dense_multiexp_glv(&pool, &bls_g, &bls_v).unwrap(), dense_multiexp(&pool, &bls_g, &bls_v).unwrap());
+}
+
+#[test]
+fn test_dense_multiexp_with_scratch() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 12;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+    let scratch = BucketScratch::new();
+
+    // Differently sized calls, so arrays are both reused and resized
+    for &len in [SAMPLES, SAMPLES, 16, SAMPLES].iter() {
+        let v = (0..len).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+        assert_eq!(
+            dense_multiexp_with_scratch(&pool, &g[..len], &v, &scratch).unwrap(),
+            dense_multiexp(&pool, &g[..len], &v).unwrap()
+        );
+    }
+    assert!(!scratch.free.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_multiexp_with_progress() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = PROGRESS_STEP + 1000;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let reports = Arc::new(Mutex::new(vec![]));
+    let callback = {
+        let reports = reports.clone();
+        Arc::new(move |processed, total| reports.lock().unwrap().push((processed, total)))
+    };
+
+    let result = multiexp_with_progress(&pool, (g.clone(), 0), FullDensity, v.clone(), callback).wait().unwrap();
+    assert_eq!(result, multiexp(&pool, (g, 0), FullDensity, v).wait().unwrap());
+
+    let regions = <Bn256 as ScalarEngine>::Fr::NUM_BITS.div_ceil(window_size(SAMPLES)) as usize;
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 2 * regions);
+    assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(reports.iter().all(|r| r.1 == SAMPLES * regions));
+    assert_eq!(reports.last().unwrap().0, SAMPLES * regions);
+}
+
+#[test]
+fn test_multiexp_with_report() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+    let (joiner, report) = multiexp_with_report(&pool, (g.clone(), 0), FullDensity, v.clone());
+
+    assert_eq!(report, MultiexpReport { c: 7, num_chunks: 37, num_bases: SAMPLES });
+    assert_eq!(joiner.wait().unwrap(), multiexp(&pool, (g, 0), FullDensity, v).wait().unwrap());
+}
+
+#[cfg(feature = "signed-buckets")]
+#[test]
+fn test_dense_multiexp_signed() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bls12_381::Bls12;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    for &samples in [1usize, 7, 1 << 10, 1 << 14].iter() {
+        let mut v = (0..samples).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng)).collect::<Vec<_>>();
+        let g = (0..samples).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+        v[0] = <Bls12 as ScalarEngine>::Fr::one();
+        v[0].negate();
+        let v = v.into_iter().map(|e| e.into_repr()).collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let signed = dense_multiexp_signed(&pool, &g, &v).unwrap();
+        let signed_time = start.elapsed();
+
+        let start = Instant::now();
+        let expected = dense_multiexp(&pool, &g, &v).unwrap();
+        println!("{} samples: signed {:?}, unsigned {:?}", samples, signed_time, start.elapsed());
+
+        assert_eq!(signed, expected);
+    }
+}
+
+#[test]
+fn test_chunks_joiner_is_send() {
+    use crate::pairing::bn256::Bn256;
+
+    fn assert_send<T: Send>() {}
+    fn assert_unpin<T: Unpin>() {}
+
+    assert_send::<ChunksJoiner<<Bn256 as Engine>::G1>>();
+    assert_unpin::<ChunksJoiner<<Bn256 as Engine>::G1>>();
+}
+
+#[test]
+fn test_chunks_joiner_wait_on_nested() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    // A single thread, taken by the outer task, so that nothing else can
+    // pick up the regions of the inner multiexp
+    let pool = Worker::new_with_cpus(1);
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+
+    let inner_pool = pool.clone();
+    let nested = pool.compute(move || {
+        multiexp(&inner_pool, (g, 0), FullDensity, v).wait_on(&inner_pool)
+    });
+
+    assert_eq!(nested.wait().unwrap(), expected);
+}
+
+#[test]
+fn test_dense_multiexp_with_digits() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    for &samples in [0usize, 1, 7, 1 << 12].iter() {
+        let v = (0..samples).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+        let g = (0..samples).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+        let num_bits = <Bn256 as ScalarEngine>::Fr::NUM_BITS;
+        assert_eq!(DigitMatrix::from_exponents_par(&pool, &v, num_bits, 11).unwrap(), DigitMatrix::from_exponents(&v, num_bits, 11).unwrap());
+        assert_eq!(dense_multiexp_with_digits(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+    }
+}
+
+#[test]
+#[ignore]
+fn test_dense_multiexp_with_digits_speed() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 20;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::new();
+
+    let start = std::time::Instant::now();
+    let shifted = dense_multiexp(&pool, &g, &v).unwrap();
+    println!("Shifting scalars taken {:?}", start.elapsed());
+
+    let start = std::time::Instant::now();
+    let precomputed = dense_multiexp_with_digits(&pool, &g, &v).unwrap();
+    println!("Precomputed digits taken {:?}", start.elapsed());
+
+    assert_eq!(shifted, precomputed);
+}
+
+#[test]
+fn test_batch_normalize() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    for &samples in [0usize, 1, 5, 1 << 10].iter() {
+        let mut points = (0..samples).map(|_| {
+            let mut p = <Bn256 as Engine>::G1::rand(rng);
+            // move away from z = 1
+            p.double();
+            p
+        }).collect::<Vec<_>>();
+        for p in points.iter_mut().step_by(3) {
+            *p = <Bn256 as Engine>::G1::zero();
+        }
+        let expected = points.iter().map(|p| p.into_affine()).collect::<Vec<_>>();
+
+        let affine = batch_normalize(&pool, &mut points);
+
+        assert_eq!(affine.len(), expected.len());
+        for (a, e) in affine.iter().zip(expected.iter()) {
+            assert_eq!(a, e);
+        }
+        for (p, e) in points.iter().zip(expected.iter()) {
+            assert_eq!(p.into_affine(), *e);
+        }
+    }
+}
+
+#[test]
+fn test_multiexp_single_cpu() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let expected = multiexp(&Worker::new_with_cpus(4), (g.clone(), 0), FullDensity, v.clone()).wait().unwrap();
+
+    for &cpus in [0usize, 1].iter() {
+        let pool = Worker::new_with_cpus(cpus);
+        assert_eq!(pool.log_num_cpus(), 0);
+        assert_eq!(multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap(), expected);
+        assert_eq!(dense_multiexp(&pool, &g, &v).unwrap(), expected);
+    }
+
+    // The regions are still queued on the pool, so they can be cancelled
+    let v = Arc::new((0..(1 << 14)).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..(1 << 14)).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+    let joiner = multiexp(&Worker::new_with_cpus(1), (g, 0), FullDensity, v);
+    joiner.cancel();
+    assert!(matches!(joiner.wait(), Err(SynthesisError::Cancelled)));
+}
+
+#[test]
+fn test_multiexp_checked_subgroup() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bls12_381::{Bls12, Fq, G1Affine};
+    use crate::pairing::ff::SqrtField;
+
+    const SAMPLES: usize = 1 << 8;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let mut g = (0..SAMPLES).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    g[3] = G1Affine::zero();
+
+    let pool = Worker::new();
+    let expected = dense_multiexp(&pool, &g, &v).unwrap();
+    assert_eq!(multiexp_checked(&pool, &g, &v, false).unwrap(), expected);
+    assert_eq!(multiexp_checked(&pool, &g, &v, true).unwrap(), expected);
+
+    // G1 has a large cofactor, so the first point found on y^2 = x^3 + 4 is
+    // all but certain to be outside of the subgroup
+    let mut x = Fq::one();
+    let outside = loop {
+        let mut rhs = x;
+        rhs.square();
+        rhs.mul_assign(&x);
+        rhs.add_assign(&Fq::from_str("4").unwrap());
+        if let Some(y) = rhs.sqrt() {
+            break G1Affine::from_xy_checked(x, y).unwrap();
+        }
+        x.add_assign(&Fq::one());
+    };
+    g[100] = outside;
+
+    assert_eq!(multiexp_checked(&pool, &g, &v, false).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
+    match multiexp_checked(&pool, &g, &v, true) {
+        Err(SynthesisError::InvalidSubgroup(100)) => {},
+        _ => panic!("expected the base outside of the subgroup to be rejected")
+    }
+}
+
+#[test]
+fn test_dense_multiexp_iter() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    for &samples in [0usize, 1, 1 << 10, STREAMING_BATCH_SIZE + 3].iter() {
+        let v = (0..samples).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng)).collect::<Vec<_>>();
+        let g = (0..samples).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+        let reprs = v.iter().map(|e| e.into_repr()).collect::<Vec<_>>();
+
+        assert_eq!(dense_multiexp_iter(&pool, &g, v.iter().map(|e| e.into_repr())).unwrap(), dense_multiexp(&pool, &g, &reprs).unwrap());
+    }
+
+    let g = (0..4).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+    let v = vec![<Bn256 as ScalarEngine>::Fr::one().into_repr(); 3];
+    match dense_multiexp_iter(&pool, &g, v.into_iter()) {
+        Err(SynthesisError::LengthMismatch { bases: 4, exponents: 3 }) => {},
+        _ => panic!("expected a length mismatch")
+    }
+}
+
+#[test]
+fn test_multiexp_serial() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bls12_381::Bls12;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    for &samples in [0usize, 1, 31, 1 << 10].iter() {
+        let v = Arc::new((0..samples).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+        let g = Arc::new((0..samples).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+        let serial = multiexp_serial((g.clone(), 0), FullDensity, &v).unwrap();
+        let parallel = multiexp(&pool, (g, 0), FullDensity, v).wait().unwrap();
+
+        assert_eq!(serial, parallel);
+    }
+}
+
+#[test]
+fn test_multiexp_density_size() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 64;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let pool = Worker::new();
+
+    // A known size is checked before any work is done
+    let mut density = DensityTracker::new();
+    for i in 0..(SAMPLES + 1) {
+        density.add_element();
+        density.inc(i);
+    }
+    let density = Arc::new(density);
+    match multiexp(&pool, (g.clone(), 0), density.clone(), v.clone()).wait() {
+        Err(SynthesisError::LengthMismatch { bases, exponents }) => {
+            assert_eq!((bases, exponents), (SAMPLES + 1, SAMPLES));
+        },
+        _ => panic!("expected a length mismatch")
+    }
+    match multiexp_serial((g.clone(), 0), density, &v) {
+        Err(SynthesisError::LengthMismatch { bases, exponents }) => {
+            assert_eq!((bases, exponents), (SAMPLES + 1, SAMPLES));
+        },
+        _ => panic!("expected a length mismatch")
+    }
 
-                    let mut guard = match this_region_rwlock.lock() {
-                        Ok(guard) => guard,
-                        Err(_) => {
-                            panic!("poisoned!"); 
-                            // poisoned.into_inner()
-                        }
-                    };
+    // An unknown size only fails once the bases run out, without panicking
+    let fewer_bases = Arc::new(g[..(SAMPLES - 1)].to_vec());
+    match multiexp(&pool, (fewer_bases, 0), FullDensity, v.clone()).wait() {
+        Err(SynthesisError::IoError(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+        _ => panic!("expected to run out of bases")
+    }
 
-                    (*guard).add_assign(&acc);
-                });
-        
-            }
-        });
+    // while extra bases are left unused
+    let fewer_exponents = Arc::new(v[..(SAMPLES - 1)].to_vec());
+    assert_eq!(
+        multiexp(&pool, (g.clone(), 0), FullDensity, fewer_exponents.clone()).wait().unwrap(),
+        dense_multiexp(&pool, &g[..(SAMPLES - 1)], &fewer_exponents).unwrap()
+    );
+}
+
+#[test]
+fn test_multiexp_with_bls12_g2() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bls12_381::Bls12;
 
-        let this_region = Arc::try_unwrap(arc).unwrap();
-        let this_region = this_region.into_inner().unwrap();
+    const SAMPLES: usize = 1 << 10;
 
-        this_region
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let v = Arc::new((0..SAMPLES).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bls12 as Engine>::G2::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let mut naive = <Bls12 as Engine>::G2::zero();
+    for (base, exp) in g.iter().zip(v.iter()) {
+        naive.add_assign(&base.mul(*exp));
+    }
+
+    let pool = Worker::new();
+
+    assert_eq!(multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap(), naive);
+    assert_eq!(dense_multiexp(&pool, &g, &v).unwrap(), naive);
+    // window tuned by hand, e.g. after timing G2 separately from G1
+    assert_eq!(multiexp_with_window(&pool, (g, 0), FullDensity, v, 9).wait().unwrap(), naive);
+}
+
+#[test]
+fn test_chunks_joiner_fold_order() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let points = (0..5).map(|_| <Bn256 as Engine>::G1::rand(rng)).collect::<Vec<_>>();
+
+    let joiner = |regions: Vec<Result<<Bn256 as Engine>::G1, SynthesisError>>| ChunksJoiner {
+        regions: regions.into_iter().map(WorkerFuture::ready).collect(),
+        acc: None,
+        c: 7,
+        cancelled: Arc::new(AtomicBool::new(false))
     };
 
-    skip += c;
+    let regions = || points.iter().map(|p| Ok(*p)).collect::<Vec<_>>();
+    assert_eq!(joiner(regions()).wait().unwrap(), join_chunks(regions(), 7).unwrap());
+    assert_eq!(joiner(vec![]).wait().unwrap(), <Bn256 as Engine>::G1::zero());
 
-    if skip >= <G::Engine as ScalarEngine>::Fr::NUM_BITS {
-        // There isn't another region, and this will be the highest region
-        return Ok(this);
-    } else {
-        // next region is actually higher than this one, so double it enough times
-        let mut next_region = dense_multiexp_inner(
-            pool, bases, exponents, skip, c, false).unwrap();
-        for _ in 0..c {
-            next_region.double();
+    // the highest failing region decides the error, as in `join_chunks`
+    let regions = vec![Ok(points[0]), Err(SynthesisError::Cancelled), Ok(points[1]), Err(SynthesisError::WorkerFailure), Ok(points[2])];
+    match joiner(regions).wait() {
+        Err(SynthesisError::WorkerFailure) => {},
+        _ => panic!("expected the error of the highest failing region")
+    }
+}
+
+#[test]
+fn test_window_size_small_field() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::tests::dummy_engine::DummyEngine;
+
+    for &len in [0usize, 1, 32, 1 << 20, u32::MAX as usize, 1 << 32, usize::MAX].iter() {
+        assert!((1..=MAX_WINDOW_SIZE).contains(&window_size(len)));
+    }
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <DummyEngine as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <DummyEngine as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let mut naive = <<DummyEngine as Engine>::G1 as CurveProjective>::zero();
+    for (base, exp) in g.iter().zip(v.iter()) {
+        CurveProjective::add_assign(&mut naive, &base.mul(*exp));
+    }
+
+    // more threads than the 16 bits of the scalar field
+    let pool = Worker::new_with_cpus(<DummyEngine as ScalarEngine>::Fr::NUM_BITS as usize + 4);
+
+    assert_eq!(multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap(), naive);
+    assert_eq!(dense_multiexp(&pool, &g, &v).unwrap(), naive);
+}
+
+#[test]
+fn test_dense_multiexp_with_cpus() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let expected = dense_multiexp(&Worker::with_cpus(1), &g, &v).unwrap();
+
+    assert_eq!(dense_multiexp(&Worker::with_cpus(4), &g, &v).unwrap(), expected);
+    assert_eq!(dense_multiexp(&Worker::with_cpus(4).with_min_chunk_size(300), &g, &v).unwrap(), expected);
+    assert_eq!(dense_multiexp(&Worker::with_cpus(4).with_min_chunk_size(SAMPLES), &g, &v).unwrap(), expected);
+}
+
+#[test]
+fn test_windows_across_limbs() {
+    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+    use crate::pairing::bn256::{Bn256, FrRepr};
+
+    const SAMPLES: usize = 1 << 8;
+    const C: u32 = 13;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    // With 13 bit windows, the regions starting at bits 52, 117 and 182 span
+    // two limbs; fill those bits with ones on top of random ones
+    let v = Arc::new((0..SAMPLES).map(|_| {
+        let mut repr = FrRepr([rng.gen(), rng.gen(), rng.gen(), rng.gen::<u64>() & 0x0fff_ffff_ffff_ffff]);
+        for &start in [52u32, 117, 182].iter() {
+            for bit in start..(start + C) {
+                repr.as_mut()[(bit / 64) as usize] |= 1 << (bit % 64);
+            }
         }
+        repr
+    }).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
 
-        next_region.add_assign(&this);
+    for exp in v.iter() {
+        for &skip in [52u32, 117, 182].iter() {
+            let mut shifted = *exp;
+            shifted.shr(skip);
+            assert_eq!(Limbs64::window(exp, skip, C), shifted.as_ref()[0] & ((1 << C) - 1));
+        }
+    }
 
-        return Ok(next_region);
+    let mut naive = <Bn256 as Engine>::G1::zero();
+    for (base, exp) in g.iter().zip(v.iter()) {
+        naive.add_assign(&base.mul(*exp));
     }
+
+    let pool = Worker::new();
+
+    assert_eq!(multiexp_with_window(&pool, (g.clone(), 0), FullDensity, v.clone(), C).wait().unwrap(), naive);
+    assert_eq!(dense_multiexp_with_window(&pool, &g, &v, C, DEFAULT_MAX_REGIONS).unwrap(), naive);
+    assert_eq!(multiexp_serial((g, 0), FullDensity, &v).unwrap(), naive);
 }
 
 #[test]
-fn test_new_multiexp_with_bls12() {
-    fn naive_multiexp<G: CurveAffine>(
-        bases: Arc<Vec<G>>,
-        exponents: Arc<Vec<<G::Scalar as PrimeField>::Repr>>
-    ) -> G::Projective
-    {
-        assert_eq!(bases.len(), exponents.len());
+#[ignore]
+fn test_multiexp_sparse_speed() {
+    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+    use crate::pairing::bn256::Bn256;
 
-        let mut acc = G::Projective::zero();
+    const SAMPLES: usize = 1 << 20;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
 
-        for (base, exp) in bases.iter().zip(exponents.iter()) {
-            acc.add_assign(&base.mul(*exp));
+    // consecutive multiples of a random point, much cheaper to make than random points
+    let step = <Bn256 as Engine>::G1::rand(rng);
+    let mut g = Vec::with_capacity(SAMPLES);
+    let mut p = step;
+    for _ in 0..SAMPLES {
+        g.push(p);
+        p.add_assign(&step);
+    }
+    let g = batch_normalize(&pool, &mut g);
+
+    // 90% of the scalars are zero
+    let v = (0..SAMPLES).map(|_| {
+        if rng.gen_range(0, 10) == 0 {
+            <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()
+        } else {
+            <Bn256 as ScalarEngine>::Fr::zero().into_repr()
         }
+    }).collect::<Vec<_>>();
 
-        acc
+    let zero = <Bn256 as ScalarEngine>::Fr::zero().into_repr();
+    let mut density = DensityTracker::new();
+    let mut dense_bases = vec![];
+    let mut nonzero = vec![];
+    for (i, exp) in v.iter().enumerate() {
+        density.add_element();
+        if *exp != zero {
+            density.inc(i);
+            dense_bases.push(g[i]);
+            nonzero.push((i, *exp));
+        }
     }
+    println!("{} of {} scalars are nonzero", nonzero.len(), SAMPLES);
 
-    use rand::{self, Rand};
-    use crate::pairing::bls12_381::Bls12;
+    let start = Instant::now();
+    let tracked = multiexp(&pool, (Arc::new(dense_bases), 0), Arc::new(density), Arc::new(v)).wait().unwrap();
+    println!("Density tracked multiexp taken {:?}", start.elapsed());
 
-    use self::futures::executor::block_on;
+    let start = Instant::now();
+    let sparse = multiexp_sparse(&pool, &g, &nonzero).unwrap();
+    println!("Sparse multiexp taken {:?}", start.elapsed());
 
-    const SAMPLES: usize = 1 << 14;
+    assert_eq!(tracked, sparse);
+}
 
-    let rng = &mut rand::thread_rng();
-    let v = Arc::new((0..SAMPLES).map(|_| <Bls12 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
-    let g = Arc::new((0..SAMPLES).map(|_| <Bls12 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+#[test]
+fn test_multiexp_skip_identity_bases() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::{Bn256, G1Affine};
 
-    let naive = naive_multiexp(g.clone(), v.clone());
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let mut g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
 
     let pool = Worker::new();
+    assert_eq!(multiexp_skip_identity_bases(&pool, &g, &v).unwrap(), dense_multiexp(&pool, &g, &v).unwrap());
 
-    let fast = block_on(
-        multiexp(
-            &pool,
-            (g, 0),
-            FullDensity,
-            v
-        )
-    ).unwrap();
+    for base in g.iter_mut().step_by(3) {
+        *base = G1Affine::zero();
+    }
+    // identities contribute nothing, same as a zero exponent
+    let zeroed = g.iter().zip(v.iter())
+        .map(|(base, exp)| if base.is_zero() { <Bn256 as ScalarEngine>::Fr::zero().into_repr() } else { *exp })
+        .collect::<Vec<_>>();
+    let expected = dense_multiexp(&pool, &g, &zeroed).unwrap();
 
-    assert_eq!(naive, fast);
+    assert_eq!(multiexp_skip_identity_bases(&pool, &g, &v).unwrap(), expected);
+    assert_eq!(dense_multiexp(&pool, &g, &v).unwrap(), expected);
+
+    let identities = vec![G1Affine::zero(); SAMPLES];
+    assert!(CurveProjective::is_zero(&multiexp_skip_identity_bases(&pool, &identities, &v).unwrap()));
 }
 
+#[cfg(feature = "profile")]
 #[test]
-#[ignore]
-fn test_new_multexp_speed_with_bn256() {
-    use rand::{self, Rand};
+fn test_dense_multiexp_with_chunk_times() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
     use crate::pairing::bn256::Bn256;
-    use num_cpus;
 
-    let cpus = num_cpus::get();
-    const SAMPLES: usize = 1 << 22;
+    const SAMPLES: usize = 1 << 12;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
 
-    let rng = &mut rand::thread_rng();
-    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
-    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+
+    let pool = Worker::with_cpus(4);
+
+    let (result, times) = dense_multiexp_with_chunk_times(&pool, &g, &v).unwrap();
+    assert_eq!(result, dense_multiexp(&pool, &g, &v).unwrap());
+    assert_eq!(times.len() as u32, <Bn256 as ScalarEngine>::Fr::NUM_BITS.div_ceil(cache_window_size::<<Bn256 as Engine>::G1Affine>(&pool, SAMPLES)));
+
+    for (region, chunks) in times.iter().enumerate() {
+        println!("region {}: {:?}", region, chunks);
+        assert_eq!(chunks.len(), 4);
+    }
+}
+
+#[ignore]
+#[test]
+fn test_dense_multiexp_skewed_speed() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
 
+    const SAMPLES: usize = 1 << 20;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
     let pool = Worker::new();
 
-    use self::futures::executor::block_on;
+    let step = <Bn256 as Engine>::G1::rand(rng);
+    let mut g = Vec::with_capacity(SAMPLES);
+    let mut p = step;
+    for _ in 0..SAMPLES {
+        g.push(p);
+        p.add_assign(&step);
+    }
+    let g = batch_normalize(&pool, &mut g);
 
-    let start = std::time::Instant::now();
+    // random scalars in the first half, zeroes and ones in the second, so a
+    // static split leaves the threads of the second half idle early
+    let v = (0..SAMPLES).map(|i| {
+        if i < SAMPLES / 2 {
+            <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()
+        } else if i % 2 == 0 {
+            <Bn256 as ScalarEngine>::Fr::zero().into_repr()
+        } else {
+            <Bn256 as ScalarEngine>::Fr::one().into_repr()
+        }
+    }).collect::<Vec<_>>();
 
-    let _fast = block_on(
-        multiexp(
-            &pool,
-            (g, 0),
-            FullDensity,
-            v
-        )
-    ).unwrap();
+    let start = Instant::now();
+    let stealing = dense_multiexp(&pool, &g, &v).unwrap();
+    println!("Work stealing multiexp taken {:?}", start.elapsed());
 
+    let scratch = BucketScratch::new();
+    let start = Instant::now();
+    let fixed = dense_multiexp_with_scratch(&pool, &g, &v, &scratch).unwrap();
+    println!("Static split multiexp taken {:?}", start.elapsed());
 
-    let duration_ns = start.elapsed().as_nanos() as f64;
-    println!("Elapsed {} ns for {} samples", duration_ns, SAMPLES);
-    let time_per_sample = duration_ns/(SAMPLES as f64);
-    println!("Tested on {} samples on {} CPUs with {} ns per multiplication", SAMPLES, cpus, time_per_sample);
+    assert_eq!(stealing, fixed);
 }
 
+#[test]
+fn test_multiexp_one_bit_window() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    for samples in 2..4 {
+        let g = (0..samples).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
+        let mut v = (0..samples).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+        // one of the scalars is trivial
+        v[0] = <Bn256 as ScalarEngine>::Fr::one().into_repr();
+
+        let mut naive = <Bn256 as Engine>::G1::zero();
+        for (base, exp) in g.iter().zip(v.iter()) {
+            naive.add_assign(&base.mul(*exp));
+        }
+
+        let one_bit = multiexp_with_window(&pool, (Arc::new(g.clone()), 0), FullDensity, Arc::new(v.clone()), 1).wait().unwrap();
+        assert_eq!(one_bit, naive);
+        assert_eq!(dense_multiexp_with_window(&pool, &g, &v, 1, DEFAULT_MAX_REGIONS).unwrap(), naive);
+
+        assert_eq!(multiexp(&pool, (Arc::new(g.clone()), 0), FullDensity, Arc::new(v.clone())).wait().unwrap(), naive);
+        assert_eq!(multiexp_serial((Arc::new(g.clone()), 0), FullDensity, &v).unwrap(), naive);
+        assert_eq!(dense_multiexp(&pool, &g, &v).unwrap(), naive);
+        assert_eq!(dense_multiexp_iter(&pool, &g, v.iter().cloned()).unwrap(), naive);
+        assert_eq!(dense_multiexp_with_scratch(&pool, &g, &v, &BucketScratch::new()).unwrap(), naive);
+        assert_eq!(dense_multiexp_with_digits(&pool, &g, &v).unwrap(), naive);
+        assert_eq!(dense_multiexp_adaptive(&pool, &g, &v).unwrap(), naive);
+        assert_eq!(dense_multiexp_glv(&pool, &g, &v).unwrap(), naive);
+        assert_eq!(multiexp_reduce_scalars(&pool, &g, &v).unwrap(), naive);
+        assert_eq!(multiexp_skip_identity_bases(&pool, &g, &v).unwrap(), naive);
+        assert_eq!(multiexp_checked(&pool, &g, &v, true).unwrap(), naive);
+
+        let nonzero = v.iter().cloned().enumerate().collect::<Vec<_>>();
+        assert_eq!(multiexp_sparse(&pool, &g, &nonzero).unwrap(), naive);
+
+        let (low, high) = multiexp_split(&pool, &g, &v, 1).unwrap();
+        let mut split = low;
+        split.add_assign(&high);
+        assert_eq!(split, naive);
+    }
+}
 
 #[test]
-fn test_dense_multiexp_vs_new_multiexp() {
-    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+fn test_multiexp_slice() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
     use crate::pairing::bn256::Bn256;
-    use num_cpus;
 
-    // const SAMPLES: usize = 1 << 22;
-    const SAMPLES: usize = 1 << 16;
+    const SAMPLES: usize = 1 << 12;
     let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
 
     let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
     let g = (0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>();
 
-    println!("Done generating test points and scalars");
+    let pool = Worker::new();
+    let borrowed = multiexp_slice(&pool, &g, &v).unwrap();
+
+    let g = Arc::new(g);
+    let v = Arc::new(v);
+    let shared = multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap();
+    assert_eq!(borrowed, shared);
+
+    assert!(matches!(
+        multiexp_slice(&pool, &g[1..], &v),
+        Err(SynthesisError::LengthMismatch { bases, exponents }) if bases == SAMPLES - 1 && exponents == SAMPLES
+    ));
+}
+
+#[test]
+fn test_scalars_from_bytes() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::{Bn256, Fr};
+
+    const SAMPLES: usize = 1 << 6;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let v = (0..SAMPLES).map(|_| Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+    let mut le = vec![];
+    let mut be = vec![];
+    for repr in v.iter() {
+        repr.write_le(&mut le).unwrap();
+        repr.write_be(&mut be).unwrap();
+    }
+    assert_eq!(le.len(), SAMPLES * 32);
+
+    assert_eq!(scalars_from_le_bytes::<Bn256>(&le).unwrap(), v);
+    assert_eq!(scalars_from_be_bytes::<Bn256>(&be).unwrap(), v);
+
+    // not a whole number of scalars
+    assert!(matches!(scalars_from_le_bytes::<Bn256>(&le[1..]), Err(SynthesisError::IoError(_))));
+
+    // the modulus itself is not canonical
+    let mut modulus = vec![];
+    Fr::char().write_be(&mut modulus).unwrap();
+    be[32..64].copy_from_slice(&modulus);
+    assert!(matches!(scalars_from_be_bytes::<Bn256>(&be), Err(SynthesisError::IoError(_))));
+}
+
+#[test]
+fn test_multiexp_g1_g2() {
+    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+    use crate::pairing::bls12_381::Bls12;
 
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
     let pool = Worker::new();
 
-    let start = std::time::Instant::now();
+    let mut density = DensityTracker::new();
+    let mut v = vec![];
+    let mut g1 = vec![];
+    let mut g2 = vec![];
+    for i in 0..SAMPLES {
+        density.add_element();
+        v.push(<Bls12 as ScalarEngine>::Fr::rand(rng).into_repr());
+        if rng.gen() {
+            density.inc(i);
+            g1.push(<Bls12 as Engine>::G1::rand(rng).into_affine());
+            g2.push(<Bls12 as Engine>::G2::rand(rng).into_affine());
+        }
+    }
+    v[0] = <Bls12 as ScalarEngine>::Fr::one().into_repr();
+    v[1] = <Bls12 as ScalarEngine>::Fr::zero().into_repr();
 
-    let dense = dense_multiexp(
-        &pool, &g, &v.clone()).unwrap();
+    let (g1, g2, density, v) = (Arc::new(g1), Arc::new(g2), Arc::new(density), Arc::new(v));
 
-    let duration_ns = start.elapsed().as_nanos() as f64;
-    println!("{} ns for dense for {} samples", duration_ns, SAMPLES);
+    let (a, b) = multiexp_g1_g2(&pool, (g1.clone(), 0), (g2.clone(), 0), density.clone(), v.clone());
+    let (a, b) = (a.wait().unwrap(), b.wait().unwrap());
 
-    use self::futures::executor::block_on;
+    assert_eq!(a, multiexp(&pool, (g1, 0), density.clone(), v.clone()).wait().unwrap());
+    assert_eq!(b, multiexp(&pool, (g2, 0), density, v).wait().unwrap());
+}
 
-    let start = std::time::Instant::now();
+#[test]
+fn test_multiexp_with_mem_limit() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::{Bn256, G1};
 
-    let sparse = block_on(
-        multiexp(
-            &pool,
-            (Arc::new(g), 0),
-            FullDensity,
-            Arc::new(v)
-        )
-    ).unwrap();
+    const SAMPLES: usize = 1 << 12;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::with_cpus(4);
+    let bucket_size = std::mem::size_of::<G1>();
 
-    let duration_ns = start.elapsed().as_nanos() as f64;
-    println!("{} ns for sparse for {} samples", duration_ns, SAMPLES);
+    // no limit to speak of
+    assert_eq!(mem_limit_window_size::<<Bn256 as Engine>::G1Affine>(&pool, SAMPLES, usize::MAX), window_size(SAMPLES));
 
-    assert_eq!(dense, sparse);
-}
\ No newline at end of file
+    // room for 4 threads with 2^5 buckets each, but not 2^6
+    let max_bytes = 4 * (1 << 5) * bucket_size + bucket_size;
+    let c = mem_limit_window_size::<<Bn256 as Engine>::G1Affine>(&pool, SAMPLES, max_bytes);
+    assert_eq!(c, 5);
+    assert!(pool.num_cpus() * (1 << c) * bucket_size <= max_bytes);
+
+    // never below a single bit
+    assert_eq!(mem_limit_window_size::<<Bn256 as Engine>::G1Affine>(&pool, SAMPLES, 0), 1);
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let limited = multiexp_with_mem_limit(&pool, (g.clone(), 0), FullDensity, v.clone(), max_bytes).wait().unwrap();
+    assert_eq!(limited, multiexp(&pool, (g, 0), FullDensity, v).wait().unwrap());
+}
+
+#[test]
+fn test_multiexp_into() {
+    use rand::{XorShiftRng, SeedableRng, Rand};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 10;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    let v = Arc::new((0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>());
+    let g = Arc::new((0..SAMPLES).map(|_| <Bn256 as Engine>::G1::rand(rng).into_affine()).collect::<Vec<_>>());
+
+    let expected = multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).wait().unwrap();
+
+    let mut acc = <Bn256 as Engine>::G1::zero();
+    multiexp_into(&pool, (g.clone(), 0), FullDensity, v.clone(), &mut acc).unwrap();
+    assert_eq!(acc, expected);
+
+    let precomputed = <Bn256 as Engine>::G1::rand(rng);
+    let mut acc = precomputed;
+    multiexp(&pool, (g.clone(), 0), FullDensity, v.clone()).add_into(&mut acc).unwrap();
+    let mut sum = precomputed;
+    sum.add_assign(&expected);
+    assert_eq!(acc, sum);
+
+    // a failed multiexp leaves the accumulator alone
+    let mut acc = precomputed;
+    let short = Arc::new(v[1..].to_vec());
+    let mut density = DensityTracker::new();
+    for i in 0..SAMPLES {
+        density.add_element();
+        density.inc(i);
+    }
+    assert!(matches!(
+        multiexp_into(&pool, (g, 0), Arc::new(density), short, &mut acc),
+        Err(SynthesisError::LengthMismatch { .. })
+    ));
+    assert_eq!(acc, precomputed);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_multiexp_rayon() {
+    use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+    use crate::pairing::bn256::Bn256;
+
+    const SAMPLES: usize = 1 << 12;
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let pool = Worker::new();
+
+    let mut density = DensityTracker::new();
+    let mut g = vec![];
+    for i in 0..SAMPLES {
+        density.add_element();
+        if rng.gen() {
+            density.inc(i);
+            g.push(<Bn256 as Engine>::G1::rand(rng).into_affine());
+        }
+    }
+    let v = (0..SAMPLES).map(|_| <Bn256 as ScalarEngine>::Fr::rand(rng).into_repr()).collect::<Vec<_>>();
+
+    let (g, density, v) = (Arc::new(g), Arc::new(density), Arc::new(v));
+    let expected = multiexp(&pool, (g.clone(), 0), density.clone(), v.clone()).wait().unwrap();
+
+    assert_eq!(multiexp_rayon((g.clone(), 0), density.clone(), &v).unwrap(), expected);
+    assert!(matches!(
+        multiexp_rayon((g, 0), density, &v[1..]),
+        Err(SynthesisError::LengthMismatch { .. })
+    ));
+}
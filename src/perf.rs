@@ -0,0 +1,132 @@
+//! Minimal reader for Linux hardware performance counters, used to
+//! measure cache behaviour of the multiexp routines. Counters are opened
+//! with `inherit` set, so threads spawned while they are running are
+//! counted too. If the kernel refuses to open a counter (no PMU access,
+//! restrictive `perf_event_paranoid`, seccomp, ...) it simply reads as zero.
+
+extern crate libc;
+
+use std::mem;
+
+/// Counts collected around a single computation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerfStats {
+    pub l3_misses: u64,
+    pub instructions: u64,
+    pub cycles: u64,
+}
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+// Last level cache misses on every architecture perf knows about
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+
+const FLAG_DISABLED: u64 = 1 << 0;
+const FLAG_INHERIT: u64 = 1 << 1;
+const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+const PERF_EVENT_IOC_RESET: libc::c_ulong = 0x2403;
+
+// First published layout of `struct perf_event_attr` (PERF_ATTR_SIZE_VER0),
+// which every kernel since 2.6.31 accepts.
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+struct Counter {
+    fd: Option<libc::c_int>,
+}
+
+impl Counter {
+    fn open(config: u64) -> Counter {
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_HARDWARE,
+            size: mem::size_of::<PerfEventAttr>() as u32,
+            config,
+            flags: FLAG_DISABLED | FLAG_INHERIT | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+            ..Default::default()
+        };
+
+        // this thread, any cpu, no group, no flags
+        let fd = unsafe {
+            libc::syscall(libc::SYS_perf_event_open, &attr as *const PerfEventAttr, 0, -1, -1, 0)
+        };
+
+        Counter {
+            fd: if fd < 0 { None } else { Some(fd as libc::c_int) }
+        }
+    }
+
+    fn ioctl(&self, request: libc::c_ulong) {
+        if let Some(fd) = self.fd {
+            unsafe { libc::ioctl(fd, request as _, 0); }
+        }
+    }
+
+    fn read(&self) -> u64 {
+        let mut value = 0u64;
+        if let Some(fd) = self.fd {
+            let read = unsafe {
+                libc::read(fd, &mut value as *mut u64 as *mut libc::c_void, mem::size_of::<u64>())
+            };
+            if read != mem::size_of::<u64>() as isize {
+                value = 0;
+            }
+        }
+
+        value
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        if let Some(fd) = self.fd {
+            unsafe { libc::close(fd); }
+        }
+    }
+}
+
+/// Run `f` with cache miss, instruction and cycle counters enabled and
+/// return its result together with the counts.
+pub fn with_perf_counters<F: FnOnce() -> R, R>(f: F) -> (R, PerfStats) {
+    let counters = [
+        Counter::open(PERF_COUNT_HW_CACHE_MISSES),
+        Counter::open(PERF_COUNT_HW_INSTRUCTIONS),
+        Counter::open(PERF_COUNT_HW_CPU_CYCLES),
+    ];
+
+    for counter in counters.iter() {
+        counter.ioctl(PERF_EVENT_IOC_RESET);
+        counter.ioctl(PERF_EVENT_IOC_ENABLE);
+    }
+
+    let result = f();
+
+    for counter in counters.iter() {
+        counter.ioctl(PERF_EVENT_IOC_DISABLE);
+    }
+
+    let stats = PerfStats {
+        l3_misses: counters[0].read(),
+        instructions: counters[1].read(),
+        cycles: counters[2].read(),
+    };
+
+    (result, stats)
+}
@@ -11,18 +11,19 @@ use std::pin::{Pin};
 use self::futures::channel::oneshot::{channel, Sender, Receiver};
 use self::futures::executor::{block_on};
 
+pub use crate::cache::CacheSizes;
+
 #[derive(Clone)]
 pub struct Worker {
     cpus: usize,
+    cache: CacheSizes,
 }
 
 impl Worker {
-    // We don't expose this outside the library so that
-    // all `Worker` instances have the same number of
-    // CPUs configured.
     pub(crate) fn new_with_cpus(_cpus: usize) -> Worker {
         Worker {
             cpus: 1,
+            cache: CacheSizes::detect(),
         }
     }
 
@@ -30,10 +31,38 @@ impl Worker {
         Self::new_with_cpus(1)
     }
 
+    /// Same as `new`, everything runs on the calling thread here.
+    pub fn with_cpus(cpus: usize) -> Worker {
+        Self::new_with_cpus(cpus)
+    }
+
+    /// Chunks always span all the items here, so this has no effect.
+    pub fn with_min_chunk_size(self, _min_chunk_size: usize) -> Worker {
+        self
+    }
+
     pub fn log_num_cpus(&self) -> u32 {
         0u32
     }
 
+    /// Number of threads `scope` splits work between, always one here.
+    pub fn num_cpus(&self) -> usize {
+        self.cpus
+    }
+
+    /// Data cache sizes detected when the worker was created.
+    pub fn cache_sizes(&self) -> CacheSizes {
+        self.cache
+    }
+
+    /// Run `f` with a worker configured for `cpus` threads. Everything runs
+    /// on the calling thread here, so this is the same as `f(self)`.
+    pub fn with_temporary_cpus<F, R>(&self, _cpus: usize, f: F) -> R
+        where F: FnOnce(&Worker) -> R
+    {
+        f(self)
+    }
+
     pub fn compute<F, T, E>(
         &self, f: F
     ) -> WorkerFuture<T, E>
@@ -53,6 +82,16 @@ impl Worker {
         worker_future
     }
 
+    /// Drive `future` to completion on the calling thread.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        block_on(future)
+    }
+
+    /// Size of the chunks that `scope` splits `elements` items into.
+    pub fn get_chunk_size(&self, elements: usize) -> usize {
+        if elements == 0 { 1 } else { elements }
+    }
+
     pub fn scope<'a, F, R>(
         &self,
         elements: usize,
@@ -60,7 +99,7 @@ impl Worker {
     ) -> R
         where F: FnOnce(&Scope<'a>, usize) -> R
     {
-        let chunk_size = if elements == 0 { 1 } else { elements };
+        let chunk_size = self.get_chunk_size(elements);
 
         let scope = Scope{
             _marker: PhantomData
@@ -114,6 +153,23 @@ impl<T: Send + 'static, E: Send + 'static> WorkerFuture<T, E> {
     pub fn wait(self) -> <Self as Future>::Output {
         block_on(self)
     }
+
+    /// Future that is already resolved to `result`, for work that was done
+    /// on the calling thread.
+    pub fn ready(result: Result<T, E>) -> Self {
+        let (sender, receiver) = channel();
+        let _ = sender.send(result);
+
+        WorkerFuture {
+            receiver
+        }
+    }
+
+    /// Tasks run as soon as they are submitted here, so there is never one
+    /// left to run.
+    pub fn run_here(&self) -> bool {
+        false
+    }
 }
 
 
@@ -69,11 +69,178 @@ impl<G: CurveAffine> Source<G> for (Arc<Vec<G>>, usize) {
     }
 }
 
+/// Bases stored column-major: all x coordinates contiguously, then all y
+/// coordinates, instead of one `(x, y)` pair per point. Points are rebuilt on
+/// the fly when they are added, so it can be used anywhere a `Vec<G>` source is.
+pub struct StructOfArraysBases<G: CurveAffine> {
+    xs: Arc<Vec<G::Base>>,
+    ys: Arc<Vec<G::Base>>,
+    infinity: Arc<BitVec>
+}
+
+impl<G: CurveAffine> Clone for StructOfArraysBases<G> {
+    fn clone(&self) -> Self {
+        StructOfArraysBases {
+            xs: self.xs.clone(),
+            ys: self.ys.clone(),
+            infinity: self.infinity.clone()
+        }
+    }
+}
+
+impl<G: CurveAffine> StructOfArraysBases<G> {
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    pub fn x_coordinates(&self) -> &[G::Base] {
+        &self.xs
+    }
+
+    pub fn y_coordinates(&self) -> &[G::Base] {
+        &self.ys
+    }
+
+    /// Reassembles the `idx`-th point.
+    pub fn get(&self, idx: usize) -> G {
+        if self.infinity[idx] {
+            G::zero()
+        } else {
+            G::from_xy_unchecked(self.xs[idx], self.ys[idx])
+        }
+    }
+}
+
+impl<G: CurveAffine> From<Vec<G>> for StructOfArraysBases<G> {
+    fn from(bases: Vec<G>) -> Self {
+        let mut xs = Vec::with_capacity(bases.len());
+        let mut ys = Vec::with_capacity(bases.len());
+        let mut infinity = BitVec::from_elem(bases.len(), false);
+
+        for (i, base) in bases.into_iter().enumerate() {
+            if base.is_zero() {
+                infinity.set(i, true);
+                xs.push(G::Base::zero());
+                ys.push(G::Base::zero());
+            } else {
+                let (x, y) = base.into_xy_unchecked();
+                xs.push(x);
+                ys.push(y);
+            }
+        }
+
+        StructOfArraysBases {
+            xs: Arc::new(xs),
+            ys: Arc::new(ys),
+            infinity: Arc::new(infinity)
+        }
+    }
+}
+
+impl<G: CurveAffine> SourceBuilder<G> for (StructOfArraysBases<G>, usize) {
+    type Source = (StructOfArraysBases<G>, usize);
+
+    fn new(self) -> (StructOfArraysBases<G>, usize) {
+        (self.0.clone(), self.1)
+    }
+}
+
+impl<G: CurveAffine> Source<G> for (StructOfArraysBases<G>, usize) {
+    fn add_assign_mixed(&mut self, to: &mut <G as CurveAffine>::Projective) -> Result<(), SynthesisError> {
+        if self.0.len() <= self.1 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected more bases when adding from source").into());
+        }
+
+        if self.0.infinity[self.1] {
+            return Err(SynthesisError::UnexpectedIdentity)
+        }
+
+        to.add_assign_mixed(&self.0.get(self.1));
+
+        self.1 += 1;
+
+        Ok(())
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        if self.0.len() <= self.1 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected more bases skipping from source").into());
+        }
+
+        self.1 += amt;
+
+        Ok(())
+    }
+}
+
+/// Source over bases that are known to be finite affine points, for example
+/// ones produced by this library. It skips the identity check that
+/// `(Arc<Vec<G>>, usize)` does for every base it reads, but running out of
+/// bases is still reported as an I/O error.
+#[derive(Clone)]
+pub struct RawAffineSource<G: CurveAffine> {
+    bases: Arc<Vec<G>>,
+    position: usize
+}
+
+impl<G: CurveAffine> RawAffineSource<G> {
+    /// Every base should be a point of the prime order subgroup other than
+    /// the identity. An identity base is not detected, it just adds nothing.
+    pub fn from_trusted(bases: Arc<Vec<G>>) -> Self {
+        RawAffineSource {
+            bases,
+            position: 0
+        }
+    }
+}
+
+impl<G: CurveAffine> SourceBuilder<G> for RawAffineSource<G> {
+    type Source = RawAffineSource<G>;
+
+    fn new(self) -> RawAffineSource<G> {
+        self
+    }
+}
+
+impl<G: CurveAffine> Source<G> for RawAffineSource<G> {
+    fn add_assign_mixed(&mut self, to: &mut <G as CurveAffine>::Projective) -> Result<(), SynthesisError> {
+        let base = self.bases.get(self.position).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "expected more bases when adding from source")
+        })?;
+
+        to.add_assign_mixed(base);
+
+        self.position += 1;
+
+        Ok(())
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        if self.bases.len() <= self.position {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected more bases skipping from source").into());
+        }
+
+        self.position += amt;
+
+        Ok(())
+    }
+}
+
 pub trait QueryDensity {
     /// Returns whether the base exists.
     type Iter: Iterator<Item=bool>;
 
     fn iter(self) -> Self::Iter;
+    /// Number of elements the density is known to cover, if any. Multiexps
+    /// check it against the number of exponents before starting and fail
+    /// with `SynthesisError::LengthMismatch` on disagreement. With `None`
+    /// nothing can be checked up front: extra density entries are ignored and
+    /// running out of bases fails with an `UnexpectedEof` I/O error, so the
+    /// caller is responsible for passing a matching number of bases.
     fn get_query_size(self) -> Option<usize>;
 }
 
@@ -138,4 +305,46 @@ impl DensityTracker {
     pub fn get_total_density(&self) -> usize {
         self.total_density
     }
-}
\ No newline at end of file
+}
+
+/// Builds the sources of `S` positioned `offset` bases ahead, so that a part of
+/// a larger base set can be processed without knowing how `S` stores it.
+#[derive(Clone)]
+pub(crate) struct OffsetSourceBuilder<S> {
+    pub(crate) inner: S,
+    pub(crate) offset: usize
+}
+
+pub(crate) struct OffsetSource<S> {
+    inner: S,
+    pending: usize
+}
+
+impl<G: CurveAffine, S: SourceBuilder<G>> SourceBuilder<G> for OffsetSourceBuilder<S> {
+    type Source = OffsetSource<S::Source>;
+
+    fn new(self) -> OffsetSource<S::Source> {
+        OffsetSource {
+            inner: self.inner.new(),
+            pending: self.offset
+        }
+    }
+}
+
+impl<G: CurveAffine, S: Source<G>> Source<G> for OffsetSource<S> {
+    fn add_assign_mixed(&mut self, to: &mut <G as CurveAffine>::Projective) -> Result<(), SynthesisError> {
+        if self.pending != 0 {
+            self.inner.skip(self.pending)?;
+            self.pending = 0;
+        }
+
+        self.inner.add_assign_mixed(to)
+    }
+
+    fn skip(&mut self, amt: usize) -> Result<(), SynthesisError> {
+        let amt = amt + self.pending;
+        self.pending = 0;
+
+        self.inner.skip(amt)
+    }
+}
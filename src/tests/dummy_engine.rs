@@ -336,6 +336,10 @@ impl Engine for DummyEngine {
     }
 }
 
+impl crate::multiexp::EndomorphismParams for DummyEngine {
+    const HAS_ENDOMORPHISM: bool = false;
+}
+
 impl CurveProjective for Fr {
     type Affine = Fr;
     type Base = Fr;